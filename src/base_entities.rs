@@ -5,23 +5,155 @@ use serde_json::Value;
 use std::{fmt, fs};
 
 use crate::modifiers::BaseModifier;
+use crate::library::ModifierLibrary;
 use crate::entities::{Delver, Defender, Dungeon};
-use colored::{Colorize, ColoredString};
+use colored::Colorize;
 
-#[derive(Deserialize, Serialize)]
+pub const PARTY_SIZE:usize = 4; // Fighter, nimble, magic and support.
+
+#[derive(Deserialize, Serialize, Clone)]
 pub struct BaseTeam {
     pub team_name:String,
     pub delvers:Vec<BaseDelver>, //This is emptied when put into GameTeam
     pub dungeon:Dungeon,
     pub defenders:Vec<BaseDefender>,
-    color:[u8;3]
+    color:[u8;3],
+    #[serde(default)]
+    pub modifiers:Vec<BaseModifier> // Held by the team as a whole, whether it's delving or defending.
 }
 impl BaseTeam {
-    pub fn load_from_file(file:&str,index:usize) -> BaseTeam {
-        let contents = fs::read_to_string(file).unwrap();
-        let teams:Value = serde_json::from_str(&contents).unwrap();
-        let mut teams:Vec<Value> = teams.as_array().unwrap().to_owned();
-        serde_json::from_value(teams[index].take()).unwrap()
+    // Teams are parsed one at a time so that a mistake can be pinned on the team and field it's in.
+    // Modifiers given by id are looked up in the library first.
+    pub fn load_all(file:&str, library:&ModifierLibrary) -> Result<Vec<BaseTeam>, TeamLoadError> {
+        let contents = fs::read_to_string(file)
+            .map_err(|error| TeamLoadError::Read {file:file.to_string(), error})?;
+        let mut teams:Value = serde_json::from_str(&contents)
+            .map_err(|error| TeamLoadError::Parse {file:file.to_string(), error})?;
+        library.resolve_teams(&mut teams);
+        let teams = match teams {
+            Value::Array(teams) => teams,
+            _ => return Err(TeamLoadError::NotAList {file:file.to_string()})
+        };
+
+        let mut results = Vec::new();
+        for (i, team) in teams.into_iter().enumerate() {
+            let team_label = match team.get("team_name").and_then(|n| n.as_str()) {
+                Some(name) => name.to_string(),
+                None => format!("team #{}", i + 1)
+            };
+            match serde_json::from_value::<BaseTeam>(team.clone()) {
+                Ok(t) => results.push(t),
+                Err(error) => {
+                    let field = find_bad_field(&team).unwrap_or_default();
+                    return Err(TeamLoadError::InvalidTeam {file:file.to_string(), team:team_label, field, message:error.to_string()})
+                }
+            }
+        }
+        Ok(results)
+    }
+    // A delving party fills all four roles, and a dungeon needs a defender to guard its last room.
+    pub fn can_delve(&self) -> bool {
+        self.delvers.len() >= PARTY_SIZE
+    }
+    pub fn can_defend(&self) -> bool {
+        !self.defenders.is_empty()
+    }
+    pub fn find<'a>(teams:&'a [BaseTeam], name:&str) -> Result<&'a BaseTeam, TeamLoadError> {
+        match teams.iter().find(|t| t.team_name == name) {
+            Some(team) => Ok(team),
+            None => Err(TeamLoadError::TeamNotFound {team:name.to_string(), available:teams.iter().map(|t| t.team_name.clone()).collect()})
+        }
+    }
+}
+
+// Serde reports what went wrong but not where, so each field is tried on its own to find the culprit.
+// Returns a path like "delvers[2]" or "color".
+fn find_bad_field(team:&Value) -> Option<String> {
+    fn check<T:serde::de::DeserializeOwned>(team:&Value, field:&str) -> Option<String> {
+        let value = match team.get(field) {
+            Some(value) => value,
+            None => return Some(field.to_string())
+        };
+        match serde_json::from_value::<T>(value.clone()) {
+            Ok(_) => None,
+            Err(_) => Some(field.to_string())
+        }
+    }
+    fn check_list<T:serde::de::DeserializeOwned>(team:&Value, field:&str) -> Option<String> {
+        if let Some(Value::Array(items)) = team.get(field) {
+            for (i, item) in items.iter().enumerate() {
+                if serde_json::from_value::<T>(item.clone()).is_err() {
+                    return Some(format!("{}[{}]", field, i))
+                }
+            }
+        }
+        check::<Vec<T>>(team, field)
+    }
+    check::<String>(team, "team_name")
+        .or_else(|| check_list::<BaseDelver>(team, "delvers"))
+        .or_else(|| check::<Dungeon>(team, "dungeon"))
+        .or_else(|| check_list::<BaseDefender>(team, "defenders"))
+        .or_else(|| check::<[u8;3]>(team, "color"))
+        .or_else(|| check_list::<BaseModifier>(team, "modifiers").filter(|_| team.get("modifiers").is_some()))
+}
+
+#[derive(Debug)]
+pub enum TeamLoadError {
+    Read {file:String, error:std::io::Error},
+    Parse {file:String, error:serde_json::Error},
+    NotAList {file:String},
+    InvalidTeam {file:String, team:String, field:String, message:String},
+    InvalidModifier {file:String, modifier:String, message:String}, // In a modifier library. modifier is empty if the whole file is wrong.
+    TeamNotFound {team:String, available:Vec<String>},
+    NoDelvers {team:String},
+    TooFewDelvers {team:String, found:usize, needed:usize},
+    NoDefenders {team:String}
+}
+impl TeamLoadError {
+    // The team file at fault, for the errors that come from reading one.
+    pub fn file(&self) -> Option<&str> {
+        match self {
+            TeamLoadError::Read {file, ..} | TeamLoadError::Parse {file, ..} |
+            TeamLoadError::NotAList {file} | TeamLoadError::InvalidTeam {file, ..} |
+            TeamLoadError::InvalidModifier {file, ..} => Some(file),
+            _ => None
+        }
+    }
+}
+impl fmt::Display for TeamLoadError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            TeamLoadError::Read {file, error} => write!(f, "Could not read {}: {}", file, error),
+            TeamLoadError::Parse {file, error} => write!(f, "{} is not valid JSON: {}", file, error),
+            TeamLoadError::NotAList {file} => write!(f, "{} should contain a list of teams", file),
+            TeamLoadError::InvalidTeam {file, team, field, message} => {
+                if field.is_empty() {
+                    write!(f, "{}: {} is invalid: {}", file, team, message)
+                } else {
+                    write!(f, "{}: {} has an invalid {}: {}", file, team, field, message)
+                }
+            }
+            TeamLoadError::InvalidModifier {file, modifier, message} => {
+                if modifier.is_empty() {
+                    write!(f, "{}: {}", file, message)
+                } else {
+                    write!(f, "{}: modifier {} {}", file, modifier, message)
+                }
+            }
+            TeamLoadError::TeamNotFound {team, available} => write!(f, "No team named \"{}\". Available teams: {}", team, available.join(", ")),
+            TeamLoadError::NoDelvers {team} => write!(f, "{} have no delvers", team),
+            TeamLoadError::TooFewDelvers {team, found, needed} => write!(f, "{} have {} delvers, but a delving party needs {}", team, found, needed),
+            TeamLoadError::NoDefenders {team} => write!(f, "{} have no defenders", team)
+        }
+    }
+}
+impl std::error::Error for TeamLoadError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            TeamLoadError::Read {error, ..} => Some(error),
+            TeamLoadError::Parse {error, ..} => Some(error),
+            _ => None
+        }
     }
 }
 impl fmt::Display for BaseTeam {
@@ -37,7 +169,7 @@ impl fmt::Display for BaseTeam {
 
 // ------------------- Base Characters -------------------
 
-#[derive(Deserialize, Serialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 pub struct BaseDefender {
     pub name: String,
     pub exploriness: f32,
@@ -60,7 +192,7 @@ impl fmt::Display for BaseDefender {
     }
 }
 
-#[derive(Deserialize, Serialize, Clone, Debug)]
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
 pub struct BaseDelver {
     pub name: String,
     pub exploriness: f32,
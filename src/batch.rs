@@ -0,0 +1,207 @@
+// Many headless games of one matchup, boiled down to statistics. Every game runs through the real Sim,
+// so the numbers follow the rules as they are rather than an approximation of them.
+
+use std::collections::BTreeMap;
+use std::fmt;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+use crate::base_entities::{BaseTeam, TeamLoadError};
+use crate::entities::{DelverTeam, DefenderTeam};
+use crate::sim::{Game, Sim, GameResult, Winner};
+
+pub fn new_sim(delvers:&BaseTeam, defenders:&BaseTeam) -> Result<Sim, TeamLoadError> {
+    let game = Game::new_game(DelverTeam::load_team(delvers)?, DefenderTeam::load_team(defenders)?);
+    Ok(Sim::new_sim(game))
+}
+
+// Plays one game to the end. The finished Sim is returned alongside the result for anything the result doesn't cover.
+// A SimError can only come from strict mode, where it means the rules made an event they can't resolve. That's a
+// bug in the rules rather than in the teams, so it panics with the details instead of being returned.
+pub fn play_headless(delvers:&BaseTeam, defenders:&BaseTeam, seed:u64) -> Result<(GameResult, Sim), TeamLoadError> {
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let mut sim = new_sim(delvers, defenders)?;
+    let result = sim.run_headless(&mut rng).unwrap_or_else(|error| panic!("Seed {}: {}", seed, error));
+    Ok((result, sim))
+}
+
+pub struct SimulationReport {
+    pub delver_team:String,
+    pub defender_team:String,
+    pub first_seed:u64,
+    pub games:u32,
+    pub delver_wins:u32,
+    pub depths:BTreeMap<i8, u32>,
+    pub total_turns:u64,
+    pub delvers:Vec<String>,
+    pub deaths:Vec<u32> // Indexed like delvers.
+}
+
+// Game n is played with seed first_seed + n, so any single game can be watched again with --seed.
+pub fn simulate(delvers:&BaseTeam, defenders:&BaseTeam, games:u32, first_seed:u64) -> Result<SimulationReport, TeamLoadError> {
+    let mut report = SimulationReport {
+        delver_team:delvers.team_name.clone(),
+        defender_team:defenders.team_name.clone(),
+        first_seed, games,
+        delver_wins:0,
+        depths:BTreeMap::new(),
+        total_turns:0,
+        delvers:Vec::new(),
+        deaths:Vec::new()
+    };
+    for n in 0..games {
+        let (result, sim) = play_headless(delvers, defenders, first_seed.wrapping_add(n as u64))?;
+        if report.delvers.is_empty() {
+            report.delvers = sim.game.delverteam.delvers.iter().map(|d| d.name().to_string()).collect();
+            report.deaths = vec![0; report.delvers.len()];
+        }
+
+        if result.winner == Winner::Delvers {report.delver_wins += 1}
+        *report.depths.entry(result.depth).or_insert(0) += 1;
+        report.total_turns += result.turns as u64;
+        for (i, d) in sim.game.delverteam.delvers.iter().enumerate() {
+            if !d.active {report.deaths[i] += 1}
+        }
+    }
+    Ok(report)
+}
+
+// With no games played, the rates are 0 and the interval covers everything.
+impl SimulationReport {
+    pub fn win_rate(&self) -> f64 {
+        if self.games == 0 {return 0.0}
+        self.delver_wins as f64 / self.games as f64
+    }
+    // 95% Wilson score interval for the delvers' win rate. Unlike the normal approximation it stays inside [0, 1]
+    // for lopsided matchups.
+    pub fn win_rate_interval(&self) -> (f64, f64) {
+        if self.games == 0 {return (0.0, 1.0)}
+        let z = 1.96;
+        let n = self.games as f64;
+        let p = self.win_rate();
+        let denominator = 1.0 + z * z / n;
+        let centre = (p + z * z / (2.0 * n)) / denominator;
+        let spread = z * (p * (1.0 - p) / n + z * z / (4.0 * n * n)).sqrt() / denominator;
+        (centre - spread, centre + spread)
+    }
+    pub fn average_turns(&self) -> f64 {
+        if self.games == 0 {return 0.0}
+        self.total_turns as f64 / self.games as f64
+    }
+    pub fn death_rate(&self, delver:usize) -> f64 {
+        if self.games == 0 {return 0.0}
+        self.deaths[delver] as f64 / self.games as f64
+    }
+}
+
+impl fmt::Display for SimulationReport {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        if self.games == 0 {
+            return write!(f, "No games of {} delving into {}'s dungeon were played", self.delver_team, self.defender_team)
+        }
+        let last_seed = self.first_seed.wrapping_add(self.games as u64 - 1);
+        writeln!(f, "{} games of {} delving into {}'s dungeon (seeds {} to {})", self.games, self.delver_team, self.defender_team, self.first_seed, last_seed)?;
+        let (low, high) = self.win_rate_interval();
+        writeln!(f, "Delver win rate: {:.1}% (95% CI {:.1}% - {:.1}%)", self.win_rate() * 100.0, low * 100.0, high * 100.0)?;
+        writeln!(f, "Average turns: {:.1}", self.average_turns())?;
+        writeln!(f, "Depth reached:")?;
+        for (depth, count) in &self.depths {
+            writeln!(f, "    {}: {:>6} ({:.1}%)", depth, count, *count as f64 / self.games as f64 * 100.0)?;
+        }
+        write!(f, "Delver death rate:")?;
+        for (i, name) in self.delvers.iter().enumerate() {
+            write!(f, "\n    {}: {:.1}%", name, self.death_rate(i) * 100.0)?;
+        }
+        Ok(())
+    }
+}
+
+// ------------------------------ League ------------------------------
+
+pub struct Standing {
+    pub team:String,
+    pub delves:u32,
+    pub delve_wins:u32,
+    pub defends:u32,
+    pub defend_wins:u32,
+    pub total_depth:u64 // Summed over this team's delves.
+}
+impl Standing {
+    pub fn wins(&self) -> u32 {
+        self.delve_wins + self.defend_wins
+    }
+    pub fn losses(&self) -> u32 {
+        self.delves + self.defends - self.wins()
+    }
+    pub fn average_depth(&self) -> f64 {
+        if self.delves == 0 {return 0.0}
+        self.total_depth as f64 / self.delves as f64
+    }
+}
+
+pub struct LeagueTable {
+    pub standings:Vec<Standing>, // Best first.
+    pub games_per_pairing:u32,
+    pub skipped:Vec<String> // Teams that sat out part of the league, and why.
+}
+
+// Every team that can delve plays games_per_pairing games in the dungeon of every other team that can defend.
+// The winner of each game, delvers or defenders, is credited with a win. Seeds count up from first_seed.
+pub fn league(teams:&[BaseTeam], games_per_pairing:u32, first_seed:u64) -> Result<LeagueTable, TeamLoadError> {
+    let mut standings: Vec<Standing> = teams.iter().map(|t| Standing {
+        team:t.team_name.clone(), delves:0, delve_wins:0, defends:0, defend_wins:0, total_depth:0
+    }).collect();
+
+    let mut seed = first_seed;
+    for (d, delvers) in teams.iter().enumerate() {
+        if !delvers.can_delve() {continue}
+        for (g, defenders) in teams.iter().enumerate() {
+            if d == g || !defenders.can_defend() {continue}
+            for _ in 0..games_per_pairing {
+                let (result, _) = play_headless(delvers, defenders, seed)?;
+                seed = seed.wrapping_add(1);
+
+                standings[d].delves += 1;
+                standings[g].defends += 1;
+                standings[d].total_depth += result.depth.max(0) as u64;
+                match result.winner {
+                    Winner::Delvers => standings[d].delve_wins += 1,
+                    Winner::Defenders => standings[g].defend_wins += 1
+                }
+            }
+        }
+    }
+
+    let mut skipped = Vec::new();
+    for t in teams {
+        match (t.can_delve(), t.can_defend()) {
+            (false, false) => skipped.push(format!("{} can neither delve nor defend", t.team_name)),
+            (false, true) => skipped.push(format!("{} has too few delvers to delve", t.team_name)),
+            (true, false) => skipped.push(format!("{} has no defenders to defend with", t.team_name)),
+            (true, true) => ()
+        }
+    }
+
+    standings.sort_by(|a, b| b.wins().cmp(&a.wins())
+        .then(b.average_depth().total_cmp(&a.average_depth())));
+    Ok(LeagueTable {standings, games_per_pairing, skipped})
+}
+
+impl fmt::Display for LeagueTable {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let width = self.standings.iter().map(|s| s.team.len()).max().unwrap_or(0).max(4);
+        writeln!(f, "League standings ({} games per pairing)", self.games_per_pairing)?;
+        write!(f, "{:>3}  {:<width$}  {:>6}  {:>6}  {:>11}  {:>12}  {:>9}", "#", "Team", "Wins", "Losses", "Delve wins", "Defend wins", "Avg depth")?;
+        for (rank, s) in self.standings.iter().enumerate() {
+            let delve_wins = format!("{}/{}", s.delve_wins, s.delves);
+            let defend_wins = format!("{}/{}", s.defend_wins, s.defends);
+            write!(f, "\n{:>3}  {:<width$}  {:>6}  {:>6}  {:>11}  {:>12}  {:>9.2}",
+                rank + 1, s.team, s.wins(), s.losses(), delve_wins, defend_wins, s.average_depth())?;
+        }
+        for s in &self.skipped {
+            write!(f, "\nNote: {}", s)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,236 @@
+// Command line parsing. Kept dependency free, the option set is small.
+
+pub const USAGE: &str = "Usage: delvers [command] [options]
+
+Commands:
+    play                   Play a game in the terminal (default)
+    simulate               Play many games of a matchup headlessly and report statistics
+    league                 Have every team delve every other team's dungeon and print the standings
+    validate               Check the team file for mistakes without playing, listing every problem found
+    replay <file>          Play a recorded game again and check it still happens the same way
+    modifiers              List the modifiers in the modifier library
+
+Options:
+    --teams <file>         Team file to load (default: Teams.json)
+    --modifiers <file>     Modifier library that team files can name modifiers from by id
+                           (default: Modifiers.json, if there is one)
+    --delvers <name>       team_name of the delving team (default: first team in the file)
+    --defenders <name>     team_name of the defending team (default: second team in the file)
+    --seed <number>        Seed the game's rng, replaying the game printed with that seed
+    --list                 List the teams in the team file and exit
+    -h, --help             Show this message
+
+Play options:
+    --headless             Play the game instantly and only print the result
+    --resume               Continue the game saved in GameSave.json and rngsave.json, with its own teams and seed
+    --autosave <frames>    Save the game every <frames> frames, when it's played in the terminal
+    --output <format>      text (default) renders the game for people. jsonl plays it instantly, writing one
+                           JSON object per resolved event with the game state after it
+    --record <file>        Save a replay of the game to <file> once it ends
+    --strict               Check every event against the game before resolving it, stopping with the details
+                           of the first one that is invalid. Also applies to replay
+
+Simulate and league options:
+    --games <number>       Number of games to play, per pairing in a league (default: 1000 for simulate, 100 for league)";
+
+pub enum Command {
+    Play (PlayOptions),
+    Simulate (SimulateOptions),
+    League (LeagueOptions),
+    Validate {teams_file:String, modifiers_file:Option<String>},
+    Replay {replay_file:String, strict:bool},
+    ListTeams {teams_file:String, modifiers_file:Option<String>},
+    ListModifiers {modifiers_file:Option<String>},
+    Help
+}
+
+pub struct TeamOptions {
+    pub teams_file:String,
+    pub modifiers_file:Option<String>,
+    pub delvers:Option<String>,
+    pub defenders:Option<String>
+}
+
+#[derive(PartialEq, Eq)]
+pub enum OutputFormat {
+    Text,
+    JsonLines
+}
+
+pub struct PlayOptions {
+    pub teams:TeamOptions,
+    pub seed:Option<u64>,
+    pub headless:bool,
+    pub resume:bool,
+    pub autosave:Option<u32>,
+    pub output:OutputFormat,
+    pub record:Option<String>,
+    pub strict:bool
+}
+
+pub struct SimulateOptions {
+    pub teams:TeamOptions,
+    pub seed:Option<u64>,
+    pub games:u32
+}
+
+pub struct LeagueOptions {
+    pub teams_file:String,
+    pub modifiers_file:Option<String>,
+    pub seed:Option<u64>,
+    pub games:u32
+}
+
+pub fn parse_args(args:impl Iterator<Item = String>) -> Result<Command, String> {
+    let mut args = args.peekable();
+    let command = match args.peek().map(|a| a.as_str()) {
+        Some("play") | Some("simulate") | Some("league") | Some("validate") | Some("replay") | Some("modifiers") => args.next().unwrap(),
+        Some(arg) if !arg.starts_with('-') => return Err(format!("Unknown command '{}'", arg)),
+        _ => String::from("play")
+    };
+    let replay_file = match command.as_str() {
+        "replay" => match args.next() {
+            Some(file) if !file.starts_with('-') => Some(file),
+            Some(arg) if arg == "-h" || arg == "--help" => return Ok(Command::Help),
+            _ => return Err(String::from("replay expects the replay file to play"))
+        },
+        _ => None
+    };
+
+    let mut teams_file = String::from("Teams.json");
+    let mut modifiers_file = None;
+    let mut delvers = None;
+    let mut defenders = None;
+    let mut seed = None;
+    let mut headless = false;
+    let mut resume = false;
+    let mut autosave = None;
+    let mut output = OutputFormat::Text;
+    let mut record = None;
+    let mut strict = false;
+    let mut games = None;
+    let mut list = false;
+    let mut new_game_flag = None; // The first flag given that sets up a new game, which --resume doesn't start.
+
+    while let Some(arg) = args.next() {
+        if !applies_to(&command, &arg) {
+            return Err(format!("{} does not apply to {}", arg, command))
+        }
+        if matches!(arg.as_str(), "--teams" | "--modifiers" | "--delvers" | "--defenders" | "--seed") && new_game_flag.is_none() {
+            new_game_flag = Some(arg.clone());
+        }
+        match arg.as_str() {
+            "--teams" => teams_file = expect_value(&arg, args.next())?,
+            "--modifiers" => modifiers_file = Some(expect_value(&arg, args.next())?),
+            "--delvers" => delvers = Some(expect_value(&arg, args.next())?),
+            "--defenders" => defenders = Some(expect_value(&arg, args.next())?),
+            "--seed" => seed = Some(parse_seed(&expect_value(&arg, args.next())?)?),
+            "--headless" => headless = true,
+            "--resume" => resume = true,
+            "--autosave" => autosave = Some(parse_count(&expect_value(&arg, args.next())?)?),
+            "--output" => output = parse_output(&expect_value(&arg, args.next())?)?,
+            "--record" => record = Some(expect_value(&arg, args.next())?),
+            "--strict" => strict = true,
+            "--games" => games = Some(parse_count(&expect_value(&arg, args.next())?)?),
+            "--list" => list = true,
+            "-h" | "--help" => return Ok(Command::Help),
+            _ => return Err(format!("Unknown argument '{}'", arg))
+        }
+    }
+
+    if list {
+        return Ok(Command::ListTeams {teams_file, modifiers_file})
+    }
+    if let Some(replay_file) = replay_file {
+        return Ok(Command::Replay {replay_file, strict})
+    }
+    if resume && record.is_some() {
+        return Err(String::from("--record can't be used with --resume, a save doesn't keep the teams it started from"))
+    }
+    if let (true, Some(flag)) = (resume, &new_game_flag) {
+        return Err(format!("{} can't be used with --resume, the saved game already has its teams and seed", flag))
+    }
+    if autosave.is_some() && (headless || output == OutputFormat::JsonLines) {
+        return Err(String::from("--autosave only applies to games played in the terminal, not with --headless or --output jsonl"))
+    }
+    if command == "modifiers" {
+        return Ok(Command::ListModifiers {modifiers_file})
+    }
+    if command == "validate" {
+        return Ok(Command::Validate {teams_file, modifiers_file})
+    }
+    if command == "league" {
+        return Ok(Command::League (LeagueOptions {teams_file, modifiers_file, seed, games:games.unwrap_or(100)}))
+    }
+    let teams = TeamOptions {teams_file, modifiers_file, delvers, defenders};
+    match command.as_str() {
+        "simulate" => Ok(Command::Simulate (SimulateOptions {teams, seed, games:games.unwrap_or(1000)})),
+        _ => Ok(Command::Play (PlayOptions {teams, seed, headless, resume, autosave, output, record, strict}))
+    }
+}
+
+// Flags that only make sense for one command are rejected on the others rather than silently ignored.
+fn applies_to(command:&str, arg:&str) -> bool {
+    match arg {
+        "-h" | "--help" => true,
+        "--strict" => command == "play" || command == "replay",
+        _ if command == "replay" => false, // Everything else it needs is in the replay file.
+        _ if command == "modifiers" => arg == "--modifiers",
+        "--headless" | "--resume" | "--autosave" | "--output" | "--record" => command == "play",
+        "--games" => command == "simulate" || command == "league",
+        "--delvers" | "--defenders" => command == "play" || command == "simulate",
+        "--seed" => command != "validate",
+        _ => true
+    }
+}
+
+fn expect_value(flag:&str, value:Option<String>) -> Result<String, String> {
+    match value {
+        Some(value) => Ok(value),
+        None => Err(format!("{} expects a value", flag))
+    }
+}
+
+fn parse_seed(value:&str) -> Result<u64, String> {
+    value.parse().map_err(|_| format!("Invalid seed '{}', expected a whole number", value))
+}
+
+fn parse_count(value:&str) -> Result<u32, String> {
+    match value.parse() {
+        Ok(0) | Err(_) => Err(format!("Invalid count '{}', expected a positive whole number", value)),
+        Ok(count) => Ok(count)
+    }
+}
+
+fn parse_output(value:&str) -> Result<OutputFormat, String> {
+    match value {
+        "text" => Ok(OutputFormat::Text),
+        "jsonl" => Ok(OutputFormat::JsonLines),
+        _ => Err(format!("Unknown output format '{}', expected text or jsonl", value))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args:&str) -> Result<Command, String> {
+        parse_args(args.split_whitespace().map(String::from))
+    }
+
+    #[test]
+    fn autosave_needs_a_game_in_the_terminal() {
+        assert!(matches!(parse("--autosave 3"), Ok(Command::Play (PlayOptions {autosave:Some(3), ..}))));
+        assert!(parse("--headless --autosave 3").is_err());
+        assert!(parse("--autosave 3 --output jsonl").is_err());
+    }
+
+    #[test]
+    fn resume_takes_nothing_that_starts_a_new_game() {
+        assert!(matches!(parse("--resume --autosave 3"), Ok(Command::Play (PlayOptions {resume:true, ..}))));
+        for flag in ["--teams Other.json", "--modifiers Other.json", "--delvers Crabs", "--defenders Pirates", "--seed 3"] {
+            let error = parse(&format!("--resume {}", flag)).err().unwrap();
+            assert!(error.starts_with(flag.split(' ').next().unwrap()), "{}", error);
+        }
+    }
+}
@@ -1,6 +1,6 @@
 use serde::{Serialize, Deserialize};
 use crate::base_entities::{BaseDefender};
-use crate::entities::{Defender, Stats};
+use crate::entities::Defender;
 impl Defender {
     pub fn create_monster(name:String, difficulty:f32, hp:i8, maxhp:i8) -> Defender{
         let mut monster_base = BaseDefender::new_delver(name);
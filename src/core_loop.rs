@@ -2,23 +2,24 @@ use rand::Rng;
 use rand::seq::{SliceRandom, IteratorRandom};
 use serde::{Serialize, Deserialize};
 
-use crate::room_types::{Coordinate};
-use crate::events::{Event, EventType, Outcomes};
+use crate::events::{Event, EventType, Outcomes, Timing, Roller, Opposition};
 use crate::entities::{Entity, Stats, Room};
-use crate::sim::{Sim, roll};
+use crate::sim::Sim;
 use crate::messaging::Message;
+use crate::modifiers::BaseModifier;
 
-#[derive(PartialEq, Eq, Serialize, Deserialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum GamePhase {
     NotStarted,
     TurnStart,
     Encounter,
     Delve,
     Finished,
-    Combat {source:Entity, target:Entity}
+    Combat {source:Entity, target:Entity},
+    Upkeep // Statuses and held events from the start of the turn are resolving. Combatants are picked after.
 }
 
-pub fn tick(sim: &mut Sim, rng:&mut impl Rng) -> () {
+pub fn tick(sim: &mut Sim, rng:&mut impl Rng) {
     // let current_room: &Room = 
     // // match sim.game.rooms.get(&sim.game.delver_position) { //Checks for if off-map
     // //     Some(n) => n,
@@ -28,29 +29,25 @@ pub fn tick(sim: &mut Sim, rng:&mut impl Rng) -> () {
     match sim.game.phase {
         GamePhase::NotStarted => {
             sim.game.phase = GamePhase::Encounter;
+            sim.game.current_room = Room::entrance(rng, &sim.game.defenderteam.dungeon);
 
             let message = Message::Delving;
             sim.eventqueue.log(message);
         }
         GamePhase::TurnStart => {
-            if sim.game.defenderteam.active_defenders.len() > 0 {
-                let defenders = 0..sim.game.defenderteam.active_defenders.len();
-                let (source, target) = 
-                if rng.gen_bool(0.5) { // Defender attacks
-                    let active_delvers = sim.game.delverteam.active_delvers();
-                    let target =  Entity::Delver{index:*active_delvers.choose(rng).unwrap()};
-                    let source = sim.game.defenderteam.choose_defender(Stats::Fightiness);
-                    (source, target)
-                } else { // Delvers attack
-                    let source = sim.game.delverteam.choose_delver(Stats::Fightiness);
-                    let target = Entity::Defender {index:defenders.choose(rng).unwrap()};
-                    (source, target)
-                };
-                sim.game.phase = GamePhase::Combat {source, target};
+            sim.game.turns += 1;
+            sim.eventqueue.start_turn();
+            for entity in sim.game.statused_entities() {
+                sim.eventqueue.schedule(Timing::Immediate, Event {event_type:EventType::TickStatuses, source:Entity::None, target:entity, message:Message::None});
+            }
+            // Poison can kill before anyone fights, so let it, and pick combatants from whoever is left.
+            if sim.eventqueue.is_idle() {
+                pick_combatants(sim, rng);
             } else {
-                sim.game.phase = GamePhase::Encounter;
+                sim.game.phase = GamePhase::Upkeep;
             }
         }
+        GamePhase::Upkeep => pick_combatants(sim, rng),
         GamePhase::Encounter => {
             if sim.game.current_room.complete {sim.game.phase = GamePhase::Delve;}
             else {
@@ -74,34 +71,55 @@ pub fn tick(sim: &mut Sim, rng:&mut impl Rng) -> () {
             let message = Message::FailedDelve (active_delver);
             let fail = vec![Event {event_type:EventType::Damage(1), target:active_delver, source:Entity::Dungeon, message}];
             
-            let outcomes = Outcomes{success, fail};
+            let outcomes = Outcomes::new(success, fail);
             let message = Message::BeginNavigation(active_delver);
-            let event = Event::type_and_message(EventType::Roll { difficulty: sim.game.defenderteam.dungeon.lengthiness, stat: Stats::Exploriness, outcomes}, message);
-            sim.eventqueue.events.push(event);
+            let roll = EventType::Roll {roller:Roller::Entity(active_delver), opposition:Opposition::Difficulty(sim.game.defenderteam.dungeon.lengthiness), stat:Stats::Exploriness, outcomes:Box::new(outcomes)};
+            let event = Event::type_and_message(roll, message);
+            sim.eventqueue.schedule(Timing::Immediate, event);
 
         }
         GamePhase::Finished => {}
         GamePhase::Combat {source, target} => {
             sim.game.phase = GamePhase::TurnStart;
-
-            let source_stat = source.collect_stats(&sim.game, Stats::Fightiness);
-            let target_stat = target.collect_stats(&sim.game, Stats::Fightiness);
-
-            let source_name = source.to_string(&sim.game);
-            let target_name = target.to_string(&sim.game);
-            
-
-            if roll(rng, source_stat) > roll(rng, target_stat) { //  Attack succeeds
-                let message = Message::Attack(source, target, 1);
-                let event = Event {event_type:EventType::Damage(1), source, target, message };
-                sim.eventqueue.events.push(event);
-            } else { // Attack fails
-                let message = Message::Attack(target, source, 1);
-                let event: Event = Event {event_type:EventType::Damage(1), target:source, source:target, message };
-                sim.eventqueue.events.push(event);
+            if !sim.game.is_active(source) || !sim.game.is_active(target) {
+                return // One of them fell before the blow, so there's no fight this turn.
             }
-            let message = Message::Custom(format!("{} attacks {}", source_name, target_name));
-            sim.eventqueue.log(message);
+
+            // Whoever wins the exchange lands the blow. Winning by a critical margin hits twice as hard and stuns.
+            let hit = |attacker, defender, amount| Event {event_type:EventType::Damage(amount as i8), source:attacker, target:defender, message:Message::Attack(attacker, defender, amount)};
+            let stun = |attacker, defender:Entity| {
+                let message = Message::Custom(format!("{} is left reeling", defender.to_string(&sim.game)));
+                Event {event_type:EventType::ApplyModifier(BaseModifier::Stunned {turns:1}), source:attacker, target:defender, message}
+            };
+            let outcomes = Outcomes {
+                success:vec![hit(source, target, 1)],
+                fail:vec![hit(target, source, 1)],
+                critical_success:Some(vec![hit(source, target, 2), stun(source, target)]),
+                critical_fail:Some(vec![hit(target, source, 2), stun(target, source)])
+            };
+            let message = Message::Custom(format!("{} attacks {}", source.to_string(&sim.game), target.to_string(&sim.game)));
+            let roll = EventType::Roll {roller:Roller::Entity(source), opposition:Opposition::Contested(Roller::Entity(target)), stat:Stats::Fightiness, outcomes:Box::new(outcomes)};
+            sim.eventqueue.schedule(Timing::Immediate, Event {event_type:roll, source, target, message});
         }
     }
-}
\ No newline at end of file
+}
+// With defenders about, a fight comes next. Otherwise the delvers get on with the room.
+fn pick_combatants(sim: &mut Sim, rng:&mut impl Rng) {
+    if sim.game.defenderteam.active_defenders.is_empty() {
+        sim.game.phase = GamePhase::Encounter;
+        return
+    }
+    let defenders = 0..sim.game.defenderteam.active_defenders.len();
+    let (source, target) = 
+    if rng.gen_bool(0.5) { // Defender attacks
+        let active_delvers = sim.game.delverteam.active_delvers();
+        let target =  Entity::Delver{index:*active_delvers.choose(rng).unwrap()};
+        let source = sim.game.defenderteam.choose_defender(Stats::Fightiness, &sim.game.shared_modifiers(Entity::DefenderTeam));
+        (source, target)
+    } else { // Delvers attack
+        let source = sim.game.delverteam.choose_delver(Stats::Fightiness);
+        let target = Entity::Defender {index:defenders.choose(rng).unwrap()};
+        (source, target)
+    };
+    sim.game.phase = GamePhase::Combat {source, target};
+}
@@ -1,12 +1,13 @@
 use serde::{Serialize, Deserialize};
 use std::{fmt};
 use rand::Rng;
-use colored::{Colorize, ColoredString};
-use crate::{room_types::Coordinate, sim::Game, room_types::RoomType, combat::Monster,
-    base_entities::{BaseDefender,BaseDelver,BaseTeam},
-    modifiers::BaseModifier};
+use rand::seq::SliceRandom;
+use colored::Colorize;
+use crate::{sim::Game, room_types::RoomType, combat::Monster,
+    base_entities::{BaseDefender,BaseDelver,BaseTeam,TeamLoadError,PARTY_SIZE},
+    modifiers::{BaseModifier, StatHolder, modified_stat}};
 
-#[derive(Clone, Copy,Debug, Deserialize, Serialize)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Deserialize, Serialize)]
 pub enum Stats {
     Exploriness,
     Fightiness,
@@ -21,14 +22,73 @@ pub enum Entity { // Losely defined as "Something that can have modifiers and be
     Dungeon,
     DelverTeam,
     DefenderTeam,
-    None
+    None,
+    Selected (Selector) // Stands in for whoever the selector picks. Replaced by them when the event fires.
 }
+
+// Picks an entity when an event fires, rather than when it's created, so the choice reflects the game at that point.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Selector {
+    RandomActiveDelver,
+    LowestHpDelver, // Active delvers only. Ties go to whoever comes first.
+    RandomDefender,
+    LowestHpDefender
+}
+impl Selector {
+    // Everyone the selector could pick.
+    pub fn candidates(&self, game:&Game) -> Vec<Entity> {
+        match self {
+            Selector::RandomActiveDelver | Selector::LowestHpDelver => {
+                game.delverteam.active_delvers().into_iter().map(|index| Entity::Delver {index}).collect()
+            }
+            Selector::RandomDefender | Selector::LowestHpDefender => {
+                (0..game.defenderteam.active_defenders.len()).map(|index| Entity::Defender {index}).collect()
+            }
+        }
+    }
+    // None if there's nobody to pick.
+    pub fn select(&self, game:&Game, rng:&mut impl Rng) -> Option<Entity> {
+        match self {
+            Selector::RandomActiveDelver | Selector::RandomDefender => self.candidates(game).choose(rng).copied(),
+            Selector::LowestHpDelver => game.delverteam.active_delvers().into_iter()
+                .min_by_key(|i| game.delverteam.delvers[*i].hp)
+                .map(|index| Entity::Delver {index}),
+            Selector::LowestHpDefender => (0..game.defenderteam.active_defenders.len())
+                .min_by_key(|i| game.defenderteam.active_defenders[*i].hp)
+                .map(|index| Entity::Defender {index})
+        }
+    }
+}
+impl fmt::Display for Selector {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Selector::RandomActiveDelver => write!(f, "a random delver"),
+            Selector::LowestHpDelver => write!(f, "the most injured delver"),
+            Selector::RandomDefender => write!(f, "a random defender"),
+            Selector::LowestHpDefender => write!(f, "the most injured defender")
+        }
+    }
+}
+
 impl Entity {
     pub fn to_string(&self, game:&Game) -> String{
         match self {
             Entity::Delver{index} => {game.delverteam.delvers[*index].to_string()},
             Entity::Defender {index} => {game.defenderteam.active_defenders[*index].to_string()},
-            _ => panic!("Invalid to_string")
+            Entity::Room => String::from("the room"),
+            Entity::Dungeon => game.defenderteam.dungeon.to_string(),
+            Entity::DelverTeam => game.delverteam.to_string(),
+            Entity::DefenderTeam => game.defenderteam.to_string(),
+            Entity::None => String::from("nobody"),
+            Entity::Selected(selector) => selector.to_string()
+        }
+    }
+    // The team a delver or defender is on.
+    pub fn team(&self) -> Option<Entity> {
+        match self {
+            Entity::Delver {..} => Some(Entity::DelverTeam),
+            Entity::Defender {..} => Some(Entity::DefenderTeam),
+            _ => None
         }
     }
     pub fn get_delver_index(self) -> usize {
@@ -39,15 +99,15 @@ impl Entity {
     }
     pub fn get_stat(&self,game:&Game, stat:Stats) -> f32 {
         match self {
-            Entity::Delver {index} => game.delverteam.delvers[*index].get_stat(stat),
-            Entity::Defender{index} => game.defenderteam.active_defenders[*index].get_stat(stat),
+            Entity::Delver {index} => game.delverteam.delvers[*index].get_stat(stat, &game.shared_modifiers(Entity::DelverTeam)),
+            Entity::Defender{index} => game.defenderteam.active_defenders[*index].get_stat(stat, &game.shared_modifiers(Entity::DefenderTeam)),
             _ => panic!("Expected delver or defender")
         }
     }
     pub fn collect_stats(&self, game:&Game, stat:Stats) -> f32 {
         match self {
-            Entity::Delver {..} => Delver::collect_stats(self, &game.delverteam.delvers, stat),
-            Entity::Defender{..} =>  Defender::collect_stats(self, &game.defenderteam.active_defenders, stat),
+            Entity::Delver {..} => Delver::collect_stats(self, &game.delverteam.delvers, &game.shared_modifiers(Entity::DelverTeam), stat),
+            Entity::Defender{..} =>  Defender::collect_stats(self, &game.defenderteam.active_defenders, &game.shared_modifiers(Entity::DefenderTeam), stat),
             _ => panic!("Expected delver or defender")
         }
     }
@@ -70,30 +130,32 @@ impl Delver {
     pub fn to_json(&self) -> String {
         serde_json::to_string(&self.base).unwrap()
     }
-    pub fn collect_stats(active_delver:&Entity, all_delvers:&Vec<Delver>, stat:Stats) -> f32 {
+    pub fn name(&self) -> &str {
+        &self.base.name
+    }
+    pub fn collect_stats(active_delver:&Entity, all_delvers:&Vec<Delver>, shared:&[&[BaseModifier]], stat:Stats) -> f32 {
         let active_delver = match active_delver {
             Entity::Delver { index } => *index,
             _ => panic!("Invalid stats collected")
         };
         let active_delver = &all_delvers[active_delver];
         let mut total = 0.0;
-        total += active_delver.get_stat(stat) * 0.75; // Active_delver should be in party, so 0.25 will also get added.
+        total += active_delver.get_stat(stat, shared) * 0.75; // Active_delver should be in party, so 0.25 will also get added.
         for d in all_delvers {
-            total += d.get_stat(stat) * 0.25;
+            total += d.get_stat(stat, shared) * 0.25;
         }
         total
     }
-    pub fn get_stat(&self, stat:Stats) -> f32 {
-        let mut statvalue =  match stat {
+    // Modifiers shared with others, like the team's and the dungeon's, apply after the character's own within each stage.
+    pub fn get_stat(&self, stat:Stats, shared:&[&[BaseModifier]]) -> f32 {
+        let statvalue = match stat {
             Stats::Exploriness => self.base.exploriness,
             Stats::Fightiness => self.base.fightiness,
             Stats::Magiciness => self.base.magiciness,
             Stats::Supportiveness => self.base.supportiveness
         };
-        for m in &self.modifiers {
-            statvalue = m.get_stat(stat, statvalue)
-        };
-        statvalue
+        let modifiers = self.modifiers.iter().chain(shared.iter().flat_map(|m| m.iter()));
+        modified_stat(modifiers, stat, statvalue, &StatHolder {hp:self.hp, maxhp:self.maxhp})
     }
 }
 
@@ -107,7 +169,7 @@ impl fmt::Display for Delver {
         write!(f, "{}", name)
     }
 }
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Defender {
     base: BaseDefender,
     pub hp:i8,
@@ -123,30 +185,32 @@ impl Defender {
     pub fn to_json(&self) -> String {
         serde_json::to_string(&self.base).unwrap()
     }
-    pub fn collect_stats(active_defender:&Entity, all_defenders:&Vec<Defender>, stat:Stats) -> f32 {
+    pub fn name(&self) -> &str {
+        &self.base.name
+    }
+    pub fn collect_stats(active_defender:&Entity, all_defenders:&Vec<Defender>, shared:&[&[BaseModifier]], stat:Stats) -> f32 {
         let active_defender = match active_defender {
             Entity::Defender { index } => *index,
             _ => panic!("Invalid stats collected")
         };
         let active_defender = &all_defenders[active_defender];
         let mut total: f32 = 0.0;
-        total += active_defender.get_stat(stat) * 0.75; // Active_delver should be in party, so 0.25 will also get added.
+        total += active_defender.get_stat(stat, shared) * 0.75; // Active_delver should be in party, so 0.25 will also get added.
         for d in all_defenders {
-            total += d.get_stat(stat) * 0.25;
+            total += d.get_stat(stat, shared) * 0.25;
         }
         total
     }
-    pub fn get_stat(&self, stat:Stats) -> f32 {
-        let mut statvalue =  match stat {
+    // Modifiers shared with others, like the team's and the dungeon's, apply after the character's own within each stage.
+    pub fn get_stat(&self, stat:Stats, shared:&[&[BaseModifier]]) -> f32 {
+        let statvalue = match stat {
             Stats::Exploriness => self.base.exploriness,
             Stats::Fightiness => self.base.fightiness,
             Stats::Magiciness => self.base.magiciness,
             Stats::Supportiveness => self.base.supportiveness
         };
-        for m in &self.modifiers {
-            statvalue = m.get_stat(stat, statvalue)
-        };
-        statvalue
+        let modifiers = self.modifiers.iter().chain(shared.iter().flat_map(|m| m.iter()));
+        modified_stat(modifiers, stat, statvalue, &StatHolder {hp:self.hp, maxhp:self.maxhp})
     }
 }
 impl fmt::Display for Defender {
@@ -168,10 +232,13 @@ impl fmt::Display for Defender {
 #[derive(Serialize, Deserialize)]
 pub struct Room {
     pub complete:bool,
-    pub room_type:RoomType
+    pub room_type:RoomType,
+    #[serde(default)]
+    pub modifiers:Vec<BaseModifier> // Apply to everyone in the room, and every event while the delvers are in it.
 }
 impl Room {
-    pub fn new_room(rng: &mut impl Rng) -> Room {
+    // Each of the dungeon's room modifiers has its own chance of being on the room.
+    pub fn new_room(rng: &mut impl Rng, dungeon:&Dungeon) -> Room {
         let room_type = match rng.gen_range(0..3) {
             0..=1 => RoomType::Arcane,
             2..=4 => RoomType::Trapped,
@@ -181,14 +248,29 @@ impl Room {
             }
             _ => panic!("Fix the rng range"),
         };
-
-        Room {complete: false, room_type} 
+        Room {complete: false, room_type, modifiers:Room::roll_modifiers(rng, dungeon)}
+    }
+    // The room the delvers start in. Always empty, but it can have modifiers like any other.
+    pub fn entrance(rng: &mut impl Rng, dungeon:&Dungeon) -> Room {
+        Room {complete: false, room_type:RoomType::Empty, modifiers:Room::roll_modifiers(rng, dungeon)}
+    }
+    // Where the defender is fought. There's nothing to clear but them, and the last room's modifiers stay behind.
+    pub fn boss_room() -> Room {
+        Room {complete: true, room_type:RoomType::Empty, modifiers:Vec::new()}
     }
-    pub fn to_string(&self) -> String {
+    fn roll_modifiers(rng: &mut impl Rng, dungeon:&Dungeon) -> Vec<BaseModifier> {
+        dungeon.room_modifiers.iter()
+            .filter(|r| rng.gen::<f32>() < r.chance)
+            .map(|r| r.modifier.clone())
+            .collect()
+    }
+}
+impl fmt::Display for Room {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
         if self.complete {
-            "[X]".to_string()
+            write!(f, "[X]")
         } else {
-            "[ ]".to_string()
+            write!(f, "[ ]")
         }
     }
 }
@@ -202,17 +284,25 @@ pub struct DelverTeam {
     pub fighter:usize,
     pub nimble:usize,
     pub magic:usize,
-    pub support:usize
+    pub support:usize,
     // Healer, etc.
+    #[serde(default)]
+    pub modifiers:Vec<BaseModifier> // Apply to every delver, and to every event involving one.
 }
 impl DelverTeam {
-    pub fn load_team(base: &BaseTeam) -> DelverTeam {
-        let mut delvers = Vec::new();
-        delvers.push(Delver::load_delver(base.delvers[0].clone()));
-        delvers.push(Delver::load_delver(base.delvers[1].clone()));
-        delvers.push(Delver::load_delver(base.delvers[2].clone()));
-        delvers.push(Delver::load_delver(base.delvers[3].clone()));
-        DelverTeam {name:base.team_name.clone(), delvers, fighter:0, nimble:1, magic:2, support:3}
+    pub fn load_team(base: &BaseTeam) -> Result<DelverTeam, TeamLoadError> {
+        if base.delvers.is_empty() {
+            return Err(TeamLoadError::NoDelvers {team:base.team_name.clone()})
+        }
+        if base.delvers.len() < PARTY_SIZE {
+            return Err(TeamLoadError::TooFewDelvers {team:base.team_name.clone(), found:base.delvers.len(), needed:PARTY_SIZE})
+        }
+        let delvers = vec![
+            Delver::load_delver(base.delvers[0].clone()),
+            Delver::load_delver(base.delvers[1].clone()),
+            Delver::load_delver(base.delvers[2].clone()),
+            Delver::load_delver(base.delvers[3].clone())];
+        Ok(DelverTeam {name:base.team_name.clone(), delvers, fighter:0, nimble:1, magic:2, support:3, modifiers:base.modifiers.clone()})
     }
     pub fn get_index(&self, delver:&Delver) -> Option<usize> {
         let mut result = Option::None;
@@ -262,13 +352,18 @@ pub struct DefenderTeam {
     name:String,
     pub defender:BaseDefender,
     pub active_defenders:Vec<Defender>,
-    pub dungeon:Dungeon
+    pub dungeon:Dungeon,
+    #[serde(default)]
+    pub modifiers:Vec<BaseModifier> // Apply to every defender, and to every event involving one.
 }
 impl DefenderTeam {
-    pub fn load_team(base: &BaseTeam) -> DefenderTeam {
+    pub fn load_team(base: &BaseTeam) -> Result<DefenderTeam, TeamLoadError> {
         // let defender = Defender::load_defender(base.defenders[0].clone());
-        
-        DefenderTeam {name:base.team_name.clone(), defender:base.defenders[0].clone(), dungeon:base.dungeon.clone(), active_defenders:Vec::new()}
+        let defender = match base.defenders.first() {
+            Some(defender) => defender.clone(),
+            None => return Err(TeamLoadError::NoDefenders {team:base.team_name.clone()})
+        };
+        Ok(DefenderTeam {name:base.team_name.clone(), defender, dungeon:base.dungeon.clone(), active_defenders:Vec::new(), modifiers:base.modifiers.clone()})
     }
     pub fn get_index(&self, delver:&Defender) -> Option<usize> {
         let mut result = Option::None;
@@ -277,17 +372,19 @@ impl DefenderTeam {
         }
         result
     }
-    pub fn choose_defender(&self, stat:Stats) -> Entity {
-        let mut max = 0.0;        
-        let mut best = None;
-        for d in &self.active_defenders {
-            if d.active {
-                if max < d.get_stat(stat) {max = d.get_stat(stat); best = Some(d);}
-            }
+    // The defender best at the stat, the first of them on a tie, even if nobody is any good at it.
+    // Shared is as from Game::shared_modifiers, so the dungeon and room count too.
+    pub fn choose_defender(&self, stat:Stats, shared:&[&[BaseModifier]]) -> Entity {
+        let mut best:Option<(usize, f32)> = None;
+        for (index, d) in self.active_defenders.iter().enumerate() {
+            if !d.active {continue}
+            let value = d.get_stat(stat, shared);
+            if best.is_none_or(|(_, max)| max < value) {best = Some((index, value))}
+        }
+        match best {
+            Some((index, _)) => Entity::Defender { index },
+            None => panic!("All defenders dead.")
         }
-        let index = self.get_index(best.unwrap()).unwrap();
-        return Entity::Defender { index };
-        // panic!("All delvers dead.")
     }
 }
 impl fmt::Display for DefenderTeam {
@@ -303,11 +400,21 @@ pub struct Dungeon {
     pub name: String,
     pub twistiness: f32,
     pub deadliness: f32,
-    pub lengthiness: f32
+    pub lengthiness: f32,
+    #[serde(default)]
+    pub modifiers: Vec<BaseModifier>, // Apply to everyone in the dungeon, and every event in it.
+    #[serde(default)]
+    pub room_modifiers: Vec<RoomModifier>
+}
+// A modifier the dungeon's rooms may be made with.
+#[derive(Deserialize, Serialize, Clone, Debug, PartialEq)]
+pub struct RoomModifier {
+    pub chance: f32,
+    pub modifier: BaseModifier
 }
 impl Dungeon {
     pub fn new_dungeon(name: String) -> Dungeon{
-        Dungeon {name, twistiness:0.5, deadliness:0.5, lengthiness:0.5}
+        Dungeon {name, twistiness:0.5, deadliness:0.5, lengthiness:0.5, modifiers:Vec::new(), room_modifiers:Vec::new()}
     }
 }
 impl fmt::Display for Dungeon {
@@ -1,11 +1,14 @@
+use rand::Rng;
+use rand::seq::SliceRandom;
 use serde::{Serialize, Deserialize};
+use std::collections::VecDeque;
 
-use crate::entities::{Entity, Stats, Delver, Defender};
-use crate::room_types::Coordinate;
+use crate::entities::{Entity, Stats, Defender};
 use crate::sim::Game;
 use crate::messaging::Message;
+use crate::modifiers::BaseModifier;
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Event {
     pub target:Entity,
     pub source:Entity,
@@ -24,7 +27,7 @@ impl Event {
     }
 }
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum EventType {
     Damage (i8), //amount
     Heal (i8), //amount
@@ -32,58 +35,247 @@ pub enum EventType {
     Death,
     EndGame,
     Log, // Do nothing, still log.
-    Roll {difficulty:f32, stat:Stats, outcomes:Outcomes},
+    Roll {roller:Roller, opposition:Opposition, stat:Stats, outcomes:Box<Outcomes>}, // The roller becomes the event's source, and a contesting opponent its target.
     Chance {chance:f32, success:Box<Event>, fail:Box<Event>},
     // Scene {scene:Box<Scene>},
     ClearRoom,
     StartBossFight,
     SpawnDefender (Defender),
-    Tick, // Continue with core game loop. TO IMPLEMENT: Should probably error if Message is not None
-    Cancelled //"Do nothing" event. TO IMPLEMENT: Should probably error if Message is not None
+    ApplyModifier (BaseModifier), // Gives the target the modifier for the rest of the game, or until removed.
+    RemoveModifier (BaseModifier), // Takes the modifier from the target, or failing that one of the same kind (see BaseModifier::same_kind). Nothing happens if they have neither.
+    TickStatuses, // The start of a turn for the target's timed statuses: their per-turn effects go off, and they count down.
+    Tick, // Continue with core game loop. Strict mode rejects it if Message is not None
+    Cancelled //"Do nothing" event. Strict mode rejects it if Message is not None
 }
 
-#[derive(Serialize, Deserialize)]
-pub struct OutcomesWithImmediate {
-    pub immediate_success:Event,
-    pub success:Vec<Event>,
-    pub immediate_fail:Event,
-    pub fail:Vec<Event>
-}
-impl OutcomesWithImmediate {
-    pub fn get(self, bool:bool) -> (Event, Vec<Event>) {
-        if bool {
-            return (self.immediate_success, self.success)
+// Every name EventType::name gives, for checking names written in data.
+pub const EVENT_NAMES:[&str; 16] = ["Damage", "Heal", "Delve", "Death", "EndGame", "Log", "Roll", "Chance", "ClearRoom",
+    "StartBossFight", "SpawnDefender", "ApplyModifier", "RemoveModifier", "TickStatuses", "Tick", "Cancelled"];
+
+impl EventType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            EventType::Damage(..) => "Damage",
+            EventType::Heal(..) => "Heal",
+            EventType::Delve => "Delve",
+            EventType::Death => "Death",
+            EventType::EndGame => "EndGame",
+            EventType::Log => "Log",
+            EventType::Roll {..} => "Roll",
+            EventType::Chance {..} => "Chance",
+            EventType::ClearRoom => "ClearRoom",
+            EventType::StartBossFight => "StartBossFight",
+            EventType::SpawnDefender(..) => "SpawnDefender",
+            EventType::ApplyModifier(..) => "ApplyModifier",
+            EventType::RemoveModifier(..) => "RemoveModifier",
+            EventType::TickStatuses => "TickStatuses",
+            EventType::Tick => "Tick",
+            EventType::Cancelled => "Cancelled"
         }
-        else {
-            return (self.immediate_fail, self.fail)
+    }
+}
+
+// Who makes a roll. A team picks one of its members when the roll resolves, so the choice reflects who is
+// still standing by then. Rolls are made with character stats, so the entities here are delvers or defenders.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Roller {
+    Entity (Entity),
+    Team {team:Entity, selection:Selection} // Entity::DelverTeam or Entity::DefenderTeam.
+}
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Selection {
+    Specialist, // The delver whose role covers the stat, or the defender best at it.
+    Random // Any active member.
+}
+impl Roller {
+    pub fn pick(&self, game:&Game, stat:Stats, rng:&mut impl Rng) -> Entity {
+        match self {
+            Roller::Entity(entity) => *entity,
+            Roller::Team {team:Entity::DelverTeam, selection:Selection::Specialist} => game.delverteam.choose_delver(stat),
+            Roller::Team {team:Entity::DefenderTeam, selection:Selection::Specialist} => game.defenderteam.choose_defender(stat, &game.shared_modifiers(Entity::DefenderTeam)),
+            Roller::Team {team:Entity::DelverTeam, selection:Selection::Random} => {
+                Entity::Delver {index:*game.delverteam.active_delvers().choose(rng).expect("All delvers dead.")}
+            }
+            Roller::Team {team:Entity::DefenderTeam, selection:Selection::Random} => {
+                let active:Vec<usize> = (0..game.defenderteam.active_defenders.len()).collect();
+                Entity::Defender {index:*active.choose(rng).expect("No defenders to roll.")}
+            }
+            Roller::Team {team, ..} => panic!("{:?} is not a team that can roll", team)
         }
     }
 }
-#[derive(Debug, Deserialize, Serialize)]
+
+// What a roll has to beat.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Opposition {
+    Difficulty (f32), // A fixed obstacle, rolled as difficulty times a random number from 0 to 1.
+    Contested (Roller) // Someone rolling the same stat against the roller.
+}
+
+// How well a roll went, graded by the margin it beat or missed its target by.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Grade {
+    CriticalSuccess,
+    Success,
+    Failure,
+    CriticalFailure
+}
+pub const CRITICAL_MARGIN:f32 = 0.5; // Beating or missing the target by more than this is critical.
+impl Grade {
+    // A margin of exactly 0 is a failure, the roll has to beat its target.
+    pub fn from_margin(margin:f32) -> Grade {
+        if margin > CRITICAL_MARGIN {Grade::CriticalSuccess}
+        else if margin > 0.0 {Grade::Success}
+        else if margin >= -CRITICAL_MARGIN {Grade::Failure}
+        else {Grade::CriticalFailure}
+    }
+    pub fn is_success(self) -> bool {
+        matches!(self, Grade::CriticalSuccess | Grade::Success)
+    }
+}
+
+// What a roll sets off. The critical lists are optional, without one a critical result sets off the
+// ordinary list for its side.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub struct Outcomes {
     pub success:Vec<Event>,
-    pub fail:Vec<Event>
+    pub fail:Vec<Event>,
+    #[serde(default)]
+    pub critical_success:Option<Vec<Event>>,
+    #[serde(default)]
+    pub critical_fail:Option<Vec<Event>>
 }
 impl Outcomes {
-    pub fn get(self, bool:bool) -> Vec<Event> {
-        if bool {
-            return self.success
-        }
-        else {
-            return self.fail
+    pub fn new(success:Vec<Event>, fail:Vec<Event>) -> Outcomes {
+        Outcomes {success, fail, critical_success:None, critical_fail:None}
+    }
+    pub fn get(self, grade:Grade) -> Vec<Event> {
+        match grade {
+            Grade::CriticalSuccess => self.critical_success.unwrap_or(self.success),
+            Grade::Success => self.success,
+            Grade::Failure => self.fail,
+            Grade::CriticalFailure => self.critical_fail.unwrap_or(self.fail)
         }
     }
 }
+// When a scheduled event resolves relative to the rest of the queue. Slots drain in the order listed,
+// and events within a slot resolve in the order they were scheduled.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Timing {
+    #[default]
+    Immediate, // Next, after anything already scheduled immediately.
+    AfterChain, // Once the immediate events, and everything they set off, have resolved. Deaths wait here so a blow and the reactions to it land first.
+    EndOfTurn, // Once nothing else is waiting, before the game loop ticks on. EndGame waits here.
+    NextTurn // Held until the next TurnStart, then resolved ahead of everything that turn. Generic pres can ask for it.
+}
+
 #[derive(Serialize, Deserialize)]
 pub struct EventQueue
 {
-    pub events:Vec<Event> //Probably doesnt need to be a struct. Fix later.
+    immediate:VecDeque<Event>,
+    after_chain:VecDeque<Event>,
+    end_of_turn:VecDeque<Event>,
+    next_turn:VecDeque<Event>
 }
 impl EventQueue {
     pub fn new_queue() -> EventQueue {
-        EventQueue {events:Vec::new()}
+        EventQueue {immediate:VecDeque::new(), after_chain:VecDeque::new(), end_of_turn:VecDeque::new(), next_turn:VecDeque::new()}
+    }
+    pub fn schedule(&mut self, timing:Timing, event:Event) {
+        self.slot(timing).push_back(event);
+    }
+    pub fn schedule_all(&mut self, timing:Timing, events:Vec<Event>) {
+        self.slot(timing).extend(events);
     }
     pub fn log(&mut self, message:Message) {
-        self.events.push(Event::type_and_message(EventType::Log, message));
+        self.schedule(Timing::Immediate, Event::type_and_message(EventType::Log, message));
     }
-}
\ No newline at end of file
+    // The next event to resolve, or None when the game loop should tick.
+    pub fn next_event(&mut self) -> Option<Event> {
+        self.immediate.pop_front()
+            .or_else(|| self.after_chain.pop_front())
+            .or_else(|| self.end_of_turn.pop_front())
+    }
+    // Releases the events held for the next turn.
+    pub fn start_turn(&mut self) {
+        let held = std::mem::take(&mut self.next_turn);
+        for event in held.into_iter().rev() {
+            self.immediate.push_front(event);
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.immediate.len() + self.after_chain.len() + self.end_of_turn.len() + self.next_turn.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    // True when nothing is left to resolve before the next tick. Events held for the next turn don't count.
+    pub fn is_idle(&self) -> bool {
+        self.immediate.is_empty() && self.after_chain.is_empty() && self.end_of_turn.is_empty()
+    }
+    // Everything waiting, in the order it will resolve.
+    pub fn iter(&self) -> impl Iterator<Item = &Event> {
+        self.immediate.iter().chain(&self.after_chain).chain(&self.end_of_turn).chain(&self.next_turn)
+    }
+    fn slot(&mut self, timing:Timing) -> &mut VecDeque<Event> {
+        match timing {
+            Timing::Immediate => &mut self.immediate,
+            Timing::AfterChain => &mut self.after_chain,
+            Timing::EndOfTurn => &mut self.end_of_turn,
+            Timing::NextTurn => &mut self.next_turn
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn damage(amount:i8) -> Event {
+        Event::type_only(EventType::Damage(amount))
+    }
+    fn drain(queue:&mut EventQueue) -> Vec<i8> {
+        std::iter::from_fn(|| queue.next_event())
+            .map(|e| match e.event_type {EventType::Damage(amount) => amount, _ => unreachable!()})
+            .collect()
+    }
+
+    #[test]
+    fn slots_drain_in_order_whatever_order_they_were_filled() {
+        let mut queue = EventQueue::new_queue();
+        queue.schedule(Timing::EndOfTurn, damage(5));
+        queue.schedule(Timing::AfterChain, damage(3));
+        queue.schedule(Timing::Immediate, damage(1));
+        queue.schedule(Timing::AfterChain, damage(4));
+        queue.schedule(Timing::Immediate, damage(2));
+        queue.schedule(Timing::EndOfTurn, damage(6));
+        assert_eq!(drain(&mut queue), vec![1, 2, 3, 4, 5, 6]);
+    }
+
+    #[test]
+    fn events_set_off_immediately_come_before_the_after_chain() {
+        let mut queue = EventQueue::new_queue();
+        queue.schedule(Timing::Immediate, damage(1));
+        queue.schedule(Timing::AfterChain, damage(3));
+        assert_eq!(queue.next_event().map(|e| e.event_type), Some(EventType::Damage(1)));
+        queue.schedule_all(Timing::Immediate, vec![damage(2)]);
+        assert_eq!(drain(&mut queue), vec![2, 3]);
+    }
+
+    #[test]
+    fn next_turn_is_held_until_the_turn_starts_then_goes_first() {
+        let mut queue = EventQueue::new_queue();
+        queue.schedule(Timing::NextTurn, damage(1));
+        queue.schedule(Timing::NextTurn, damage(2));
+        assert!(queue.is_idle());
+        assert!(!queue.is_empty());
+        assert_eq!(queue.next_event(), None);
+
+        queue.schedule(Timing::Immediate, damage(3));
+        queue.schedule(Timing::AfterChain, damage(4));
+        queue.start_turn();
+        assert_eq!(queue.iter().count(), 4);
+        assert_eq!(drain(&mut queue), vec![1, 2, 3, 4]);
+        assert!(queue.is_empty());
+    }
+}
@@ -0,0 +1,56 @@
+// The record of everything that happened in a game, kept so it can be asked about afterwards.
+
+use serde::{Serialize, Deserialize};
+
+use crate::core_loop::GamePhase;
+use crate::entities::{Entity, Selector};
+use crate::events::{Event, EventType};
+use crate::modifiers::{BaseModifier, ModRelation};
+use crate::sim::Sim;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Effect {
+    Replaced, // replace_event changed the event, or cancelled it.
+    ChanceFailed, // replace_event tried to change the event by chance, and missed. The event went ahead, perhaps with a new message.
+    Reacted // pre_event scheduled events of its own in response.
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct ModifierEffect {
+    pub modifier:BaseModifier,
+    pub relation:ModRelation, // How the modifier's holder was involved in the event.
+    pub effect:Effect
+}
+
+// Who a selector picked. The event and anything it set off name the entity rather than the selector.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Choice {
+    pub selector:Selector,
+    pub entity:Entity // Entity::None if there was nobody to pick.
+}
+
+// One resolved event, as it was after modifiers had their say.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    pub seq:usize, // Position in the history, counting from 0.
+    pub turn:u32,
+    pub phase:GamePhase, // The phase the game was in when the event resolved.
+    pub event:Event,
+    pub message:String, // As rendered at the time. Empty if the event had no message.
+    pub modifiers:Vec<ModifierEffect>,
+    #[serde(default)]
+    pub choices:Vec<Choice>
+}
+
+impl Sim {
+    // The Death event that killed target, if they died. Its source dealt the killing blow.
+    pub fn killing_blow(&self, target:Entity) -> Option<&HistoryEntry> {
+        self.history.iter().rev().find(|e| e.event.target == target && e.event.event_type == EventType::Death)
+    }
+    // How many events the modifier changed or reacted to, across everyone holding it. Failed chances don't count.
+    pub fn modifier_activations(&self, modifier:&BaseModifier) -> usize {
+        self.history.iter()
+            .filter(|e| e.modifiers.iter().any(|m| &m.modifier == modifier && m.effect != Effect::ChanceFailed))
+            .count()
+    }
+}
@@ -0,0 +1,54 @@
+//TODO:
+// active_delver should depend on the phase, not just rotate.
+// paths ugh
+// Interface
+// Modifiers
+// Could be fun to add some tests
+
+// The simulator as a library. The delvers binary is one client of it; anything else that wants to run
+// games (bots, dashboards) can depend on this crate and use the same calls:
+//
+//     let library = ModifierLibrary::load("Modifiers.json")?; // Or ModifierLibrary::default(), for none.
+//     let teams = delvers::load_teams("Teams.json", &library)?;
+//     let mut sim = delvers::new_sim(&teams[0], &teams[1])?;
+//     let mut rng = ChaCha8Rng::seed_from_u64(seed);
+//     sim.add_listener(Box::new(...));          // Optional, to be told about each event as it happens.
+//     sim.resolve_last_event(&mut rng)?;        // One event at a time, or
+//     let result = sim.run_headless(&mut rng)?; // the whole game at once.
+//     for entry in &sim.history { ... }         // Everything that happened, either way.
+
+#![allow(clippy::wrong_self_convention)]
+mod teams;
+pub mod entities;
+pub mod base_entities;
+pub mod sim;
+pub mod room_types;
+pub mod modifiers;
+pub mod core_loop;
+pub mod events;
+pub mod history;
+pub mod listeners;
+pub mod strict;
+
+pub mod messaging;
+pub mod combat;
+pub mod batch;
+pub mod output;
+pub mod save;
+pub mod replay;
+pub mod validate;
+pub mod library;
+
+pub use crate::base_entities::{BaseTeam, TeamLoadError};
+pub use crate::library::ModifierLibrary;
+pub use crate::sim::{Game, Sim, GameResult, Winner};
+pub use crate::events::Event;
+pub use crate::history::HistoryEntry;
+pub use crate::listeners::Listener;
+pub use crate::strict::SimError;
+pub use crate::core_loop::GamePhase;
+pub use crate::batch::{new_sim, play_headless, simulate, league};
+
+pub fn load_teams(file:&str, library:&ModifierLibrary) -> Result<Vec<BaseTeam>, TeamLoadError> {
+    BaseTeam::load_all(file, library)
+}
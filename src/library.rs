@@ -0,0 +1,140 @@
+// The modifier library: modifiers defined once in their own file, and referenced from team files by id.
+// Many teams can then share one definition, and balancing it happens in one place.
+
+use serde::{Serialize, Deserialize};
+use serde_json::Value;
+use std::{fmt, fs};
+
+use crate::base_entities::TeamLoadError;
+use crate::modifiers::BaseModifier;
+
+pub const DEFAULT_FILE:&str = "Modifiers.json";
+
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Rarity {
+    #[default]
+    Common,
+    Uncommon,
+    Rare,
+    Legendary
+}
+
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct LibraryEntry {
+    pub id:String, // What team files write in perm_mods.
+    pub name:String, // Generic modifiers take this as their name. Built-in ones keep their own.
+    #[serde(default)]
+    pub description:String,
+    #[serde(default)]
+    pub rarity:Rarity,
+    pub modifier:BaseModifier
+}
+
+#[derive(Clone, Debug, Default)]
+pub struct ModifierLibrary {
+    pub entries:Vec<LibraryEntry>
+}
+
+impl ModifierLibrary {
+    // Entries are parsed one at a time, like teams, so a mistake can be pinned on the entry it's in.
+    pub fn load(file:&str) -> Result<ModifierLibrary, TeamLoadError> {
+        let invalid = |modifier:String, message:String| TeamLoadError::InvalidModifier {file:file.to_string(), modifier, message};
+        let contents = fs::read_to_string(file)
+            .map_err(|error| TeamLoadError::Read {file:file.to_string(), error})?;
+        let entries:Value = serde_json::from_str(&contents)
+            .map_err(|error| TeamLoadError::Parse {file:file.to_string(), error})?;
+        let entries = match entries {
+            Value::Array(entries) => entries,
+            _ => return Err(invalid(String::new(), String::from("should contain a list of modifiers")))
+        };
+
+        let mut library = ModifierLibrary::default();
+        for (i, entry) in entries.into_iter().enumerate() {
+            let label = match entry.get("id").and_then(|n| n.as_str()) {
+                Some(id) => id.to_string(),
+                None => format!("#{}", i + 1)
+            };
+            let mut entry = serde_json::from_value::<LibraryEntry>(entry).map_err(|e| invalid(label.clone(), format!("is invalid: {}", e)))?;
+            if library.get(&entry.id).is_some() {
+                return Err(invalid(label, String::from("is defined more than once")))
+            }
+            if let BaseModifier::Generic(generic) = &mut entry.modifier {
+                generic.name = entry.name.clone();
+            }
+            library.entries.push(entry);
+        }
+        Ok(library)
+    }
+    // The default file is optional, so without it there's simply no library. A file asked for by name isn't.
+    pub fn load_or_default(file:Option<&str>) -> Result<ModifierLibrary, TeamLoadError> {
+        match file {
+            Some(file) => ModifierLibrary::load(file),
+            None if fs::metadata(DEFAULT_FILE).is_ok() => ModifierLibrary::load(DEFAULT_FILE),
+            None => Ok(ModifierLibrary::default())
+        }
+    }
+    pub fn get(&self, id:&str) -> Option<&LibraryEntry> {
+        self.entries.iter().find(|e| e.id == id)
+    }
+    // Swaps the ids in every modifier list for the modifiers they name, in a list of teams as loaded from a
+    // team file. Anything that isn't an id is left for serde, so variant names like "Pheonix" still work.
+    pub fn resolve_teams(&self, teams:&mut Value) {
+        for team in teams.as_array_mut().into_iter().flatten() {
+            if let Some(modifiers) = team.get_mut("modifiers") {
+                self.resolve(modifiers);
+            }
+            if let Some(dungeon) = team.get_mut("dungeon") {
+                if let Some(modifiers) = dungeon.get_mut("modifiers") {
+                    self.resolve(modifiers);
+                }
+                for room in dungeon.get_mut("room_modifiers").and_then(|r| r.as_array_mut()).into_iter().flatten() {
+                    if let Some(modifier) = room.get_mut("modifier") {
+                        self.resolve_one(modifier);
+                    }
+                }
+            }
+            for field in ["delvers", "defenders"] {
+                for character in team.get_mut(field).and_then(|c| c.as_array_mut()).into_iter().flatten() {
+                    if let Some(modifiers) = character.get_mut("perm_mods") {
+                        self.resolve(modifiers);
+                    }
+                }
+            }
+        }
+    }
+    // The same, for one list of modifiers.
+    pub fn resolve(&self, modifiers:&mut Value) {
+        for modifier in modifiers.as_array_mut().into_iter().flatten() {
+            self.resolve_one(modifier);
+        }
+    }
+    pub fn resolve_one(&self, modifier:&mut Value) {
+        let entry = match modifier {
+            Value::String(id) => self.get(id),
+            _ => None
+        };
+        if let Some(entry) = entry {
+            *modifier = serde_json::to_value(&entry.modifier).unwrap();
+        }
+    }
+}
+
+impl fmt::Display for Rarity {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Rarity::Common => write!(f, "common"),
+            Rarity::Uncommon => write!(f, "uncommon"),
+            Rarity::Rare => write!(f, "rare"),
+            Rarity::Legendary => write!(f, "legendary")
+        }
+    }
+}
+impl fmt::Display for LibraryEntry {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({}, {})", self.name, self.id, self.rarity)?;
+        if !self.description.is_empty() {
+            write!(f, ": {}", self.description)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,26 @@
+// Listeners are told what a Sim does as it does it. Renderers, stat collectors and loggers register
+// themselves on the Sim instead of driving the loop in sim.rs themselves.
+
+use crate::core_loop::GamePhase;
+use crate::events::Event;
+use crate::history::HistoryEntry;
+use crate::sim::{Game, Sim, GameResult};
+
+// Every callback does nothing by default, so a listener only implements the ones it wants.
+// Listeners are called in the order they were added.
+pub trait Listener {
+    // The event about to resolve, after modifiers have replaced it. The game is as it was before.
+    fn before_event(&mut self, _event:&Event, _game:&Game) {}
+    // The event has resolved. The game is as it is after.
+    fn after_event(&mut self, _entry:&HistoryEntry, _game:&Game) {}
+    // Called after the event that caused the change, so after_event for it comes first.
+    fn phase_changed(&mut self, _from:GamePhase, _to:GamePhase, _game:&Game) {}
+    // Once, when the game reaches GamePhase::Finished. Events already queued can still resolve afterwards.
+    fn game_ended(&mut self, _result:&GameResult, _game:&Game) {}
+}
+
+impl Sim {
+    pub fn add_listener(&mut self, listener:Box<dyn Listener>) {
+        self.listeners.push(listener);
+    }
+}
@@ -1,114 +1,255 @@
-//TODO:
-// active_delver should depend on the phase, not just rotate.
-// paths ugh
-// Interface
-// Modifiers
-// Could be fun to add some tests
-
-#![allow(dead_code, unused_imports)]
-mod teams;
-mod entities;
-mod base_entities;
-mod sim;
-mod room_types;
-mod modifiers;
-mod core_loop;
-mod events;
-
-mod messaging;
-mod combat;
-
-use std::fs;
-use std::io::Write;
+mod cli;
+
+use std::io;
 use std::time;
 use std::thread;
-use std::fs::File;
-
-use std::collections::HashMap;
-use crate::events::EventQueue;
 
-use crate::base_entities::{BaseTeam, BaseDefender};
-use crate::entities::{DelverTeam, DefenderTeam};
-use crate::room_types::{Coordinate, RoomType};
-use crate::sim::{Game, Sim};
+use delvers::{save, BaseTeam, TeamLoadError, ModifierLibrary, Sim, SimError, GamePhase, GameResult};
+use delvers::output::JsonLinesWriter;
+use delvers::replay::Replay;
+use delvers::validate::{self, Severity};
+use crate::cli::{Command, OutputFormat, PlayOptions, SimulateOptions, LeagueOptions, TeamOptions};
 
 use rand::prelude::*;
 use rand_chacha::ChaCha8Rng;
 
-use rand_pcg::Pcg32;
-use serde::Deserialize;
-use serde::Serialize;
+fn main() {
+    #[cfg(windows)]
+    colored::control::set_virtual_terminal(true).unwrap();
 
-#[derive(Serialize,Deserialize)]
-struct RngSaver {
-    rng_seed:[u8;32],
-    rng_stream:u64,
-    rng_word:u128,
+    let command = match cli::parse_args(std::env::args().skip(1)) {
+        Ok(command) => command,
+        Err(message) => {
+            eprintln!("{}\n\n{}", message, cli::USAGE);
+            std::process::exit(2);
+        }
+    };
+
+    match command {
+        Command::Play(options) => play(options),
+        Command::Simulate(options) => simulate(options),
+        Command::League(options) => league(options),
+        Command::Validate {teams_file, modifiers_file} => validate(&teams_file, modifiers_file.as_deref()),
+        Command::Replay {replay_file, strict} => replay(&replay_file, strict),
+        Command::ListTeams {teams_file, modifiers_file} => list_teams(&teams_file, modifiers_file.as_deref()),
+        Command::ListModifiers {modifiers_file} => list_modifiers(modifiers_file.as_deref()),
+        Command::Help => println!("{}", cli::USAGE)
+    }
 }
-impl RngSaver {
-    fn save_rng(rng:&ChaCha8Rng) -> RngSaver {
-        let rng_seed = rng.get_seed();
-        let rng_stream = rng.get_stream();
-        let rng_word = rng.get_word_pos();
-        RngSaver { rng_seed, rng_stream, rng_word}
+
+fn play(options:PlayOptions) {
+    let (mut rng, seed, mut sim, matchup) = if options.resume {
+        match save::load(save::GAME_SAVE, save::RNG_SAVE) {
+            Ok((rng, seed, sim)) => (rng, seed, sim, None),
+            Err(message) => {
+                eprintln!("Could not resume: {}", message);
+                std::process::exit(1);
+            }
+        }
+    } else {
+        let (rng, seed, sim, matchup) = new_game(&options);
+        (rng, seed, sim, Some(matchup))
+    };
+    if options.strict {
+        sim.strict = true;
     }
-    fn load_rng(&self) -> ChaCha8Rng {
-        let mut rng = ChaCha8Rng::from_seed(self.rng_seed);
-        rng.set_stream(self.rng_stream);
-        rng.set_word_pos(self.rng_word);
-        rng
+    // println!("{} are delving into the {}'s dungeon, {}", team1.to_string(), team2.to_string(), team2.dungeon.to_string());
+
+    if options.output == OutputFormat::JsonLines {
+        eprintln!("Seed: {}", seed);
+        stream_json_lines(&mut rng, &mut sim);
+    } else {
+        println!("Seed: {}", seed);
+        if options.headless {
+            println!("{}", or_stop(sim.run_headless(&mut rng)));
+        } else {
+            play_in_terminal(&mut rng, seed, &mut sim, &options);
+        }
+    }
+
+    if let (Some(replay_file), Some((delvers, defenders))) = (&options.record, matchup) {
+        if let Err(message) = Replay::record(seed, &delvers, &defenders, &sim).save(replay_file) {
+            eprintln!("Could not save the replay: {}", message);
+            std::process::exit(1);
+        }
+        eprintln!("Replay saved to {}", replay_file);
     }
 }
 
-fn main() {
-    colored::control::set_virtual_terminal(true).unwrap();
+fn play_in_terminal(rng:&mut ChaCha8Rng, seed:u64, sim:&mut Sim, options:&PlayOptions) {
+    if options.resume {
+        println!("Resuming dlungeon!");
+    } else {
+        println!("Play dlungeon!");
+    }
+    let waittime = time::Duration::from_secs(2);
+    thread::sleep(waittime);
+
+    let mut frames:u32 = 0;
+    loop {
+        if !or_stop(sim.next_frame(rng)) {
+            break
+        }
+        // Saves land between frames, so a resumed game picks up on the frame after the last one shown.
+        frames += 1;
+        if let Some(every) = options.autosave {
+            if frames.is_multiple_of(every) {
+                save::save(save::GAME_SAVE, save::RNG_SAVE, rng, seed, sim);
+            }
+        }
+    }
+}
+
+// Resolves events one at a time, writing each to stdout as it happens. Keeps going past the end of the game
+// until the queue is empty, so the last messages make it into the stream. If the reader stops reading, like
+// `head` does, the game still plays out quietly so --record gets all of it.
+fn stream_json_lines(rng:&mut ChaCha8Rng, sim:&mut Sim) {
+    colored::control::set_override(false);
+    let writer = JsonLinesWriter::new(std::io::stdout());
+    let error = writer.error();
+    sim.add_listener(Box::new(writer));
+    while sim.game.phase != GamePhase::Finished || !sim.eventqueue.is_idle() {
+        or_stop(sim.resolve_last_event(rng));
+    }
+    match error.take() {
+        Some(error) if error.kind() != io::ErrorKind::BrokenPipe => {
+            eprintln!("Could not write the game's events: {}", error);
+            std::process::exit(1);
+        }
+        _ => ()
+    }
+}
 
+// Also returns the two teams playing, for recording a replay.
+fn new_game(options:&PlayOptions) -> (ChaCha8Rng, u64, Sim, (BaseTeam, BaseTeam)) {
+    let seed = choose_seed(options.seed);
+    let rng = ChaCha8Rng::seed_from_u64(seed);
 
-    let mut rng = rand::thread_rng();
+    let teams_file = &options.teams.teams_file;
+    let teams = load_teams(teams_file, options.teams.modifiers_file.as_deref());
+    let (team1, team2) = select_matchup(&teams, &options.teams);
+    let sim = or_exit(delvers::new_sim(team1, team2), teams_file);
+    (rng, seed, sim, (team1.clone(), team2.clone()))
+}
 
-    let mut rng = ChaCha8Rng::seed_from_u64(rng.gen());
+fn simulate(options:SimulateOptions) {
+    let seed = choose_seed(options.seed);
+    let teams_file = &options.teams.teams_file;
+    let teams = load_teams(teams_file, options.teams.modifiers_file.as_deref());
+    let (team1, team2) = select_matchup(&teams, &options.teams);
+    println!("{}", or_exit(delvers::simulate(team1, team2, options.games, seed), teams_file));
+}
 
-    let team1 = BaseTeam::load_from_file("Teams.json", 0);
-    let delver_team = DelverTeam::load_team(&team1);
-    
-    let team2 = BaseTeam::load_from_file("Teams.json", 1);
-    let defender_team = DefenderTeam::load_team(&team2);
+fn league(options:LeagueOptions) {
+    let seed = choose_seed(options.seed);
+    let teams = load_teams(&options.teams_file, options.modifiers_file.as_deref());
+    println!("{}", or_exit(delvers::league(&teams, options.games, seed), &options.teams_file));
+}
 
-    let game = Game::new_game(delver_team, defender_team);
-    let mut sim = Sim {game, finished:false, eventqueue:EventQueue::new_queue()};
-    // println!("{} are delving into the {}'s dungeon, {}", team1.to_string(), team2.to_string(), team2.dungeon.to_string());
+fn load_teams(teams_file:&str, modifiers_file:Option<&str>) -> Vec<BaseTeam> {
+    let library = or_exit(ModifierLibrary::load_or_default(modifiers_file), teams_file);
+    or_exit(BaseTeam::load_all(teams_file, &library), teams_file)
+}
 
-    println!("Play dlungeon!");
-    let waittime = time::Duration::from_secs(2);
-    thread::sleep(waittime);
+// Loading problems are mistakes in the team file, so they're reported rather than panicked on.
+fn or_exit<T>(result:Result<T, TeamLoadError>, teams_file:&str) -> T {
+    match result {
+        Ok(value) => value,
+        Err(error) => {
+            match error.file() {
+                Some(_) => eprintln!("{}", error),
+                None => eprintln!("{}: {}", teams_file, error)
+            }
+            std::process::exit(1);
+        }
+    }
+}
 
-    loop {
-        if !sim.next_frame(&mut rng) {
-            break
+// Only strict mode returns errors from the game, and they're bugs in the rules, so there's nothing to do but report them.
+fn or_stop<T>(result:Result<T, SimError>) -> T {
+    match result {
+        Ok(value) => value,
+        Err(error) => {
+            eprintln!("{}", error);
+            std::process::exit(1);
         }
+    }
+}
 
+fn choose_seed(seed:Option<u64>) -> u64 {
+    match seed {
+        Some(seed) => seed,
+        None => rand::thread_rng().gen()
     }
+}
 
+fn select_matchup<'a>(teams:&'a [BaseTeam], options:&TeamOptions) -> (&'a BaseTeam, &'a BaseTeam) {
+    let delvers = select_team(teams, &options.teams_file, options.delvers.as_deref(), 0);
+    let defenders = select_team(teams, &options.teams_file, options.defenders.as_deref(), 1);
+    (delvers, defenders)
 }
 
-fn load() -> (ChaCha8Rng, Sim) {
-    let file = std::fs::read_to_string("rngsave.json").unwrap();
-    let rngsave:RngSaver = serde_json::from_str(&file).unwrap();
-    rngsave.load_rng();
+// Picks a team by name, falling back to its position in the file when no name was given.
+fn select_team<'a>(teams:&'a [BaseTeam], teams_file:&str, name:Option<&str>, default_index:usize) -> &'a BaseTeam {
+    match name {
+        Some(name) => or_exit(BaseTeam::find(teams, name), teams_file),
+        None => match teams.get(default_index) {
+            Some(team) => team,
+            None => {
+                eprintln!("{} has no team at position {}.", teams_file, default_index + 1);
+                std::process::exit(1);
+            }
+        }
+    }
+}
 
-    let file = std::fs::read_to_string("GameSave.json").unwrap();
-    let sim = serde_json::from_str(&file).unwrap();
+fn validate(teams_file:&str, modifiers_file:Option<&str>) {
+    let library = or_exit(ModifierLibrary::load_or_default(modifiers_file), teams_file);
+    let problems = validate::validate_file(teams_file, &library);
+    for p in &problems {
+        println!("{}", p);
+    }
+    let errors = problems.iter().filter(|p| p.severity == Severity::Error).count();
+    let warnings = problems.len() - errors;
+    if errors > 0 {
+        println!("{}: {} errors, {} warnings", teams_file, errors, warnings);
+        std::process::exit(1);
+    }
+    println!("{} is valid ({} warnings)", teams_file, warnings);
+}
 
-    (rngsave.load_rng(), sim) 
+fn replay(replay_file:&str, strict:bool) {
+    let replay = match Replay::load(replay_file) {
+        Ok(replay) => replay,
+        Err(message) => {
+            eprintln!("Could not load the replay: {}", message);
+            std::process::exit(1);
+        }
+    };
+    println!("Seed: {}", replay.seed);
+    match replay.verify(strict) {
+        Ok(sim) => {
+            println!("{}", GameResult::from_game(&sim.game));
+            println!("{}: all {} events matched the recording", replay_file, replay.history.len());
+        }
+        Err(error) => {
+            println!("{}", error);
+            std::process::exit(1);
+        }
+    }
 }
 
-fn save(rng: ChaCha8Rng, sim:Sim) {
-    let game_save = serde_json::to_string_pretty(&sim).unwrap();
-    let mut file = File::create("GameSave.json").unwrap();
-    write!(file, "{}", game_save).unwrap();
+fn list_teams(teams_file:&str, modifiers_file:Option<&str>) {
+    let teams = load_teams(teams_file, modifiers_file);
+    for t in &teams {
+        println!("{} ({} delvers, {} defenders, dungeon {})", t, t.delvers.len(), t.defenders.len(), t.dungeon);
+    }
+}
 
-    let rngsaver = RngSaver::save_rng(&rng);
-    let rng_save = serde_json::to_string(&rngsaver).unwrap();
-    let mut file = File::create("rngsave.json").unwrap();
-    write!(file, "{}", rng_save).unwrap();
-}
\ No newline at end of file
+fn list_modifiers(modifiers_file:Option<&str>) {
+    let file = modifiers_file.unwrap_or(delvers::library::DEFAULT_FILE);
+    let library = or_exit(ModifierLibrary::load(file), file);
+    for entry in &library.entries {
+        println!("{}", entry);
+    }
+}
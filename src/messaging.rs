@@ -1,10 +1,10 @@
 use crate::entities::Entity;
-use crate::room_types::Coordinate;
+use crate::modifiers::BaseModifier;
 use crate::sim::Game;
 use serde::{Serialize, Deserialize};
 
 
-#[derive(Debug, Serialize, Deserialize)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum Message {
     Delving,
     Attack (Entity, Entity, u8), // Source, Target, Amount
@@ -15,21 +15,64 @@ pub enum Message {
     Custom (String),
     Death (Entity),
     Encounters (String), //defender_name (Message is applied to spawning event, before Entity exists)
+    GainsModifier (Entity, BaseModifier),
+    LosesModifier (Entity, BaseModifier),
+    WearsOff (Entity, BaseModifier),
+    Parts (Vec<MessagePart>), // Names are filled in when the message is shown, so they can name someone picked by a Selector.
     None
 }
 
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum MessagePart {
+    Text (String),
+    Name (Entity)
+}
+
 impl Message {
+    // The entities the message names.
+    pub fn entities(&self) -> Vec<Entity> {
+        match self {
+            Message::Attack(a, b, _) | Message::Heal(a, b, _) => vec![*a, *b],
+            Message::BeginNavigation(e) | Message::Delve(e) | Message::FailedDelve(e) | Message::Death(e) => vec![*e],
+            Message::GainsModifier(e, _) | Message::LosesModifier(e, _) | Message::WearsOff(e, _) => vec![*e],
+            Message::Parts(parts) => parts.iter().filter_map(|p| match p {
+                MessagePart::Name(e) => Some(*e),
+                MessagePart::Text(_) => None
+            }).collect(),
+            Message::Delving | Message::Custom(_) | Message::Encounters(_) | Message::None => Vec::new()
+        }
+    }
+    pub fn entities_mut(&mut self) -> Vec<&mut Entity> {
+        match self {
+            Message::Attack(a, b, _) | Message::Heal(a, b, _) => vec![a, b],
+            Message::BeginNavigation(e) | Message::Delve(e) | Message::FailedDelve(e) | Message::Death(e) => vec![e],
+            Message::GainsModifier(e, _) | Message::LosesModifier(e, _) | Message::WearsOff(e, _) => vec![e],
+            Message::Parts(parts) => parts.iter_mut().filter_map(|p| match p {
+                MessagePart::Name(e) => Some(e),
+                MessagePart::Text(_) => None
+            }).collect(),
+            Message::Delving | Message::Custom(_) | Message::Encounters(_) | Message::None => Vec::new()
+        }
+    }
     pub fn to_string(&self, game:&Game) -> String {
         match self {
-            Message::Attack(attacker, reciever, _amount) => attacker.to_string(game) + " injures " + &reciever.to_string(game),
+            Message::Attack(attacker, reciever, 1) => attacker.to_string(game) + " injures " + &reciever.to_string(game),
+            Message::Attack(attacker, reciever, _amount) => attacker.to_string(game) + " badly injures " + &reciever.to_string(game),
             Message::Delving => String::from("The ") + &game.delverteam.to_string() + " are delving into the " + &game.defenderteam.to_string() + "'s dungeon, " + &game.defenderteam.dungeon.to_string(),
             Message::Heal (healer, reciever, _amount) => healer.to_string(game) + " heals " + &reciever.to_string(game),
             Message::BeginNavigation(navigator) => navigator.to_string(game) + " begins trying to navigate to the next room.",
             Message::Delve(navigator) => navigator.to_string(game) + " guides the delvers deeper.",
             Message::FailedDelve(navigator) => navigator.to_string(game) + " hurts themselves while navigating.",
             Message::Custom(message) => message.clone(),
+            Message::Parts(parts) => parts.iter().map(|p| match p {
+                MessagePart::Text(text) => text.clone(),
+                MessagePart::Name(entity) => entity.to_string(game)
+            }).collect(),
             Message::Death(dier) => dier.to_string(game) + " dies.",
-            Message::Encounters(defender_name) => String::from("The party encounters a ") + &defender_name,
+            Message::Encounters(defender_name) => String::from("The party encounters a ") + defender_name,
+            Message::GainsModifier(entity, modifier) => format!("{} gains {}.", entity.to_string(game), modifier),
+            Message::LosesModifier(entity, modifier) => format!("{} loses {}.", entity.to_string(game), modifier),
+            Message::WearsOff(entity, modifier) => format!("{}'s {} wears off.", entity.to_string(game), modifier),
             Message::None => game.last_log_message.clone()
         }
     }
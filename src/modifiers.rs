@@ -1,19 +1,22 @@
 use serde::{Serialize, Deserialize};
+use std::fmt;
+use std::mem;
 
-use crate::events::{Event, EventType, EventQueue, OutcomesWithImmediate};
+use crate::events::{Event, EventType, EventQueue, Timing, Opposition, Grade, EVENT_NAMES};
 use crate::sim::Game;
-use crate::entities::{Entity,Stats};
-use crate::messaging::Message;
+use crate::entities::{Entity,Stats,Selector};
+use crate::messaging::{Message, MessagePart};
 
 pub struct ModToApply <'a> {
     pub modifier: &'a BaseModifier,
     pub relation: ModRelation
 }
-#[derive(Clone, Copy, PartialEq)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
 pub enum ModRelation {
     Target, Source,
-    Team,
-    // FriendlyDungeon, EnemyDungeon
+    Team, // Held by the team of the event's source or target.
+    Dungeon, // Held by the dungeon, which every event happens in.
+    Room // Held by the room the delvers are in.
 }
 pub enum ReplaceOutcomes{
     Stop,
@@ -21,17 +24,67 @@ pub enum ReplaceOutcomes{
     Chance {chance:f32, success:Event, fail:Event}
 }
 
-#[derive(Clone, Serialize, Deserialize, Debug)]
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum BaseModifier {
     Pheonix,
     CheeseThirst,
-    TrailBlazer
+    TrailBlazer,
+    Cursed,
+    // Timed statuses. Each counts down at the start of every turn, and wears off at the start of the turn
+    // after it reaches 0, so a status applied mid-turn lasts through as many whole turns as it has.
+    // Applying one that's already held refreshes it to the longer of the two, rather than stacking.
+    Poisoned {turns:u8}, // Takes 1 damage at the start of each turn.
+    Stunned {turns:u8}, // Can't attack, or fight back when attacked.
+    Shielded {turns:u8}, // The next damage taken is absorbed, using up the shield.
+    Regenerating {turns:u8}, // Heals 1 at the start of each turn.
+    Generic (Box<GenericModifier>) // Defined in data rather than code. See GenericModifier below.
 }
 impl BaseModifier {
+    // Same variant, whatever the fields hold, so Poisoned {turns:1} and Poisoned {turns:3} are the same kind.
+    // Generic modifiers are only the same kind if they have the same name.
+    pub fn same_kind(&self, other:&BaseModifier) -> bool {
+        match (self, other) {
+            (BaseModifier::Generic(a), BaseModifier::Generic(b)) => a.name == b.name,
+            _ => mem::discriminant(self) == mem::discriminant(other)
+        }
+    }
+    pub fn is_timed(&self) -> bool {
+        matches!(self, BaseModifier::Poisoned {..} | BaseModifier::Stunned {..} | BaseModifier::Shielded {..} | BaseModifier::Regenerating {..})
+    }
+    // Takes a turn off a timed status. True if it had none left to take, and should wear off.
+    pub fn count_down(&mut self) -> bool {
+        match self.turns_mut() {
+            Some(0) => true,
+            Some(turns) => {*turns -= 1; false}
+            None => false
+        }
+    }
+    // Keeps the longer of two timed statuses of the same kind.
+    pub fn refresh(&mut self, other:&BaseModifier) {
+        let other_turns = other.turns();
+        if let (Some(turns), Some(other_turns)) = (self.turns_mut(), other_turns) {
+            *turns = (*turns).max(other_turns);
+        }
+    }
+    fn turns(&self) -> Option<u8> {
+        match self {
+            BaseModifier::Poisoned {turns} | BaseModifier::Stunned {turns} | BaseModifier::Shielded {turns} | BaseModifier::Regenerating {turns} => Some(*turns),
+            _ => None
+        }
+    }
+    fn turns_mut(&mut self) -> Option<&mut u8> {
+        match self {
+            BaseModifier::Poisoned {turns} | BaseModifier::Stunned {turns} | BaseModifier::Shielded {turns} | BaseModifier::Regenerating {turns} => Some(turns),
+            _ => None
+        }
+    }
     pub fn replace_event(&self, event:Event, relation:ModRelation, game:&Game, queue:&mut EventQueue) -> ReplaceOutcomes {
         match self {
             BaseModifier::Pheonix => pheonix::replace_event(event, relation, game, queue),
             BaseModifier::TrailBlazer => trail_blazer::replace_event(event, relation, game, queue),
+            BaseModifier::Stunned {..} => stunned::replace_event(event, relation, game, queue),
+            BaseModifier::Shielded {..} => shielded::replace_event(self, event, relation, game, queue),
+            BaseModifier::Generic(generic) => generic.replace_event(event, relation),
             _ => ReplaceOutcomes::Event {event}
         }
     }
@@ -39,16 +92,59 @@ impl BaseModifier {
         match self {
             BaseModifier::CheeseThirst => cheese_thirst::pre_event(event, relation, game, queue),
             BaseModifier::TrailBlazer => trail_blazer::pre_event(event, relation, game, queue),
+            BaseModifier::Poisoned {turns} if *turns > 0 => poisoned::pre_event(event, relation, game, queue),
+            BaseModifier::Regenerating {turns} if *turns > 0 => regenerating::pre_event(event, relation, game, queue),
+            BaseModifier::Generic(generic) => generic.pre_event(event, relation, queue),
             _ => ()
         }
     }
-    pub fn get_stat(&self, stat:Stats, statvalue:f32) -> f32 {
-        match self {
-            BaseModifier::TrailBlazer => trail_blazer::get_stat(stat, statvalue),
+    pub fn get_stat(&self, stat:Stats, statvalue:f32, stage:StatStage, holder:&StatHolder) -> f32 {
+        match (self, stage) {
+            (BaseModifier::TrailBlazer, StatStage::Add) => trail_blazer::get_stat(stat, statvalue),
+            (BaseModifier::Cursed, StatStage::Add) => cursed::get_stat(stat, statvalue),
+            (BaseModifier::Generic(generic), _) => generic.get_stat(stat, statvalue, stage, holder),
             _ => statvalue
         }
     }
 }
+// Stat changes apply in stages, so a stat doesn't depend on the order its modifiers were gained in: every Set,
+// then every Add, then every Multiply, then every Clamp. The built-in modifiers all apply at Add.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum StatStage {Set, Add, Multiply, Clamp}
+const STAT_STAGES:[StatStage; 4] = [StatStage::Set, StatStage::Add, StatStage::Multiply, StatStage::Clamp];
+
+// What a stat modifier may need to know about whoever holds it.
+pub struct StatHolder {
+    pub hp:i8,
+    pub maxhp:i8
+}
+
+// A stat with every modifier applied. Within a stage, modifiers apply in the order given.
+pub fn modified_stat<'a>(modifiers:impl Iterator<Item = &'a BaseModifier> + Clone, stat:Stats, base:f32, holder:&StatHolder) -> f32 {
+    let mut statvalue = base;
+    for stage in STAT_STAGES {
+        for m in modifiers.clone() {
+            statvalue = m.get_stat(stat, statvalue, stage, holder)
+        }
+    }
+    statvalue
+}
+
+impl fmt::Display for BaseModifier {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            BaseModifier::Pheonix => write!(f, "Pheonix"),
+            BaseModifier::CheeseThirst => write!(f, "Cheese Thirst"),
+            BaseModifier::TrailBlazer => write!(f, "Trail Blazer"),
+            BaseModifier::Cursed => write!(f, "a curse"),
+            BaseModifier::Poisoned {..} => write!(f, "poison"),
+            BaseModifier::Stunned {..} => write!(f, "stun"),
+            BaseModifier::Shielded {..} => write!(f, "shield"),
+            BaseModifier::Regenerating {..} => write!(f, "regeneration"),
+            BaseModifier::Generic(generic) => write!(f, "{}", generic.name)
+        }
+    }
+}
 
 
 
@@ -83,13 +179,10 @@ mod cheese_thirst {
     use crate::modifiers::*;
     pub fn pre_event(event:&Event, relation:ModRelation,  game:&Game, queue:&mut EventQueue) {
         if relation == ModRelation::Source {
-        match event.event_type {
-            EventType::Death => {
-                let message = Message::Custom(format!("{} devours their cheese", event.source.to_string(game)));
-                let event = Event {event_type:EventType::Heal(2), target:event.source, source:event.source, message};
-                queue.events.push(event);
-            },
-            _ => ()
+        if let EventType::Death = event.event_type {
+            let message = Message::Custom(format!("{} devours their cheese", event.source.to_string(game)));
+            let event = Event {event_type:EventType::Heal(2), target:event.source, source:event.source, message};
+            queue.schedule(Timing::Immediate, event);
         }
     }
     }
@@ -107,21 +200,18 @@ mod trail_blazer {
             event.message = Message::Custom(format!("{} burns a trail forward.", event.source.to_string(game)));
             return ReplaceOutcomes::Event { event };
         }
-        return ReplaceOutcomes::Event { event };
+        ReplaceOutcomes::Event { event }
     }
     pub fn pre_event(event:&Event, relation:ModRelation,  game:&Game, queue:&mut EventQueue) {
         if relation == ModRelation::Source {
-        match event.event_type {
-            EventType::Delve => {
-                let success = Box::new(Event::cancelled());
-                
-                let message = Message::Custom(format!("{} burns up slightly.", event.source.to_string(game)));
-                let fail = Box::new(Event {event_type:EventType::Damage(1), target:event.source, source:event.source, message});
+        if let EventType::Delve = event.event_type {
+            let success = Box::new(Event::cancelled());
+            
+            let message = Message::Custom(format!("{} burns up slightly.", event.source.to_string(game)));
+            let fail = Box::new(Event {event_type:EventType::Damage(1), target:event.source, source:event.source, message});
 
-                let event = Event::type_only(EventType::Chance {chance:0.5, success, fail});
-                queue.events.push(event);
-            },
-            _ => ()
+            let event = Event::type_only(EventType::Chance {chance:0.5, success, fail});
+            queue.schedule(Timing::Immediate, event);
         }
     }
     }
@@ -134,12 +224,78 @@ mod trail_blazer {
 }
 
 
+mod cursed {
+    use crate::modifiers::*;
+    pub fn get_stat(_stat:Stats, statvalue:f32) -> f32 {
+        (statvalue - 0.1).max(0.0)
+    }
+}
+
+mod poisoned {
+    use crate::modifiers::*;
+    pub fn pre_event(event:&Event, relation:ModRelation,  game:&Game, queue:&mut EventQueue) {
+        if relation == ModRelation::Target && event.event_type == EventType::TickStatuses {
+            let message = Message::Custom(format!("{} suffers from the poison", event.target.to_string(game)));
+            queue.schedule(Timing::Immediate, Event {event_type:EventType::Damage(1), target:event.target, source:Entity::None, message});
+        }
+    }
+}
+
+mod stunned {
+    use crate::modifiers::*;
+    // Combat is a contested Fightiness roll made by the attacker, so a stunned attacker's roll never happens.
+    // A stunned target can't fight back, so unless the attacker is stunned too, the attack simply lands.
+    pub fn replace_event(event:Event, relation:ModRelation, game:&Game, queue:&mut EventQueue) -> ReplaceOutcomes {
+        match (relation, event.event_type) {
+            (ModRelation::Source, EventType::Roll {opposition:Opposition::Contested(_), stat:Stats::Fightiness, ..}) => {
+                let message = Message::Custom(format!("{} is stunned, and can't attack", event.source.to_string(game)));
+                ReplaceOutcomes::Event {event:Event::type_and_message(EventType::Log, message)}
+            }
+            (ModRelation::Target, EventType::Roll {opposition:Opposition::Contested(_), stat:Stats::Fightiness, outcomes, ..})
+                if !game.modifiers(event.source).iter().any(|m| matches!(m, BaseModifier::Stunned {..})) => {
+                queue.schedule_all(Timing::Immediate, outcomes.get(Grade::Success));
+                let message = Message::Custom(format!("{} is stunned, and can't fight back", event.target.to_string(game)));
+                ReplaceOutcomes::Event {event:Event::type_and_message(EventType::Log, message)}
+            }
+            (_, event_type) => ReplaceOutcomes::Event {event:Event {event_type, ..event}}
+        }
+    }
+}
+
+mod shielded {
+    use crate::modifiers::*;
+    pub fn replace_event(shield:&BaseModifier, event:Event, relation:ModRelation, game:&Game, queue:&mut EventQueue) -> ReplaceOutcomes {
+        match (relation, &event.event_type) {
+            (ModRelation::Target, EventType::Damage(_)) => {
+                let used_up = Event {event_type:EventType::RemoveModifier(shield.clone()), target:event.target, source:event.source, message:Message::None};
+                queue.schedule(Timing::Immediate, used_up);
+                let message = Message::Custom(format!("{}'s shield absorbs the blow", event.target.to_string(game)));
+                ReplaceOutcomes::Event {event:Event::type_and_message(EventType::Log, message)}
+            }
+            _ => ReplaceOutcomes::Event {event}
+        }
+    }
+}
+
+mod regenerating {
+    use crate::modifiers::*;
+    pub fn pre_event(event:&Event, relation:ModRelation,  game:&Game, queue:&mut EventQueue) {
+        if relation == ModRelation::Target && event.event_type == EventType::TickStatuses {
+            let message = Message::Custom(format!("{} regenerates", event.target.to_string(game)));
+            queue.schedule(Timing::Immediate, Event {event_type:EventType::Heal(1), target:event.target, source:event.target, message});
+        }
+    }
+}
+
 
 
-#[derive(Debug,Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 enum GenericEntity {
     Source,
     Target,
+    Selected(Selector), // Picked when the event fires, not when it's made.
+    DelverTeam, // Damage or healing aimed at a team hits each of its members.
+    DefenderTeam,
     None
 }
 impl GenericEntity {
@@ -147,44 +303,42 @@ impl GenericEntity {
         match self {
             GenericEntity::Target => triggering_event.target,
             GenericEntity::Source => triggering_event.source,
+            GenericEntity::Selected(selector) => Entity::Selected(*selector),
+            GenericEntity::DelverTeam => Entity::DelverTeam,
+            GenericEntity::DefenderTeam => Entity::DefenderTeam,
             GenericEntity::None => Entity::None
         }
     }
 }
-#[derive(Debug,Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 enum GenericString {
     Phrase(String),
     EntityName(GenericEntity)
 }
 impl GenericString {
-    fn to_string(&self, triggering_event:&Event, game:&Game) -> String {
+    fn to_part(&self, triggering_event:&Event) -> MessagePart {
         match self {
-            GenericString::Phrase(str) => str.clone(),
-            GenericString::EntityName(entity) => entity.to_entity(triggering_event).to_string(game)
+            GenericString::Phrase(str) => MessagePart::Text(str.clone()),
+            GenericString::EntityName(entity) => MessagePart::Name(entity.to_entity(triggering_event))
         }
     }
 }
-#[derive(Debug,Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 enum GenericMessage {
     Default (Message),
     Custom (Vec<GenericString>)
 }
 impl GenericMessage {
-    fn to_message(self, triggering_event:&Event, game:&Game) -> Message {
+    // Names are left as entities, so a selected entity is named once it has been picked.
+    fn to_message(self, triggering_event:&Event) -> Message {
         match self {
             GenericMessage::Default(message) => message,
-            GenericMessage::Custom(strings) => {
-                let mut base = String::new();
-                for i in strings {
-                    base += &i.to_string(triggering_event, game)
-                };
-                Message::Custom(base)
-            }
+            GenericMessage::Custom(strings) => Message::Parts(strings.iter().map(|i| i.to_part(triggering_event)).collect())
         }
     }
 }
 
-#[derive(Debug,Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 struct GenericEvent {
     target:GenericEntity,
     source:GenericEntity,
@@ -193,56 +347,156 @@ struct GenericEvent {
 }
 
 impl GenericEvent {
-    fn to_event(self, triggering_event:&Event, game:&Game) -> Event{
+    fn to_event(self, triggering_event:&Event) -> Event{
         Event { target: self.target.to_entity(triggering_event),
                 source: self.source.to_entity(triggering_event),
-                message:self.message.to_message(triggering_event, game),
+                message:self.message.to_message(triggering_event),
                 event_type: self.event_type}
     }
 }
-#[derive(Debug,Deserialize, Serialize)]
+// Generic modifiers are written as data, in Teams.json, instead of in Rust. Each part is triggered by an event
+// held in the given relation, whose type has the given name (as EventType::name gives it, so "Damage" matches
+// any Damage whatever the amount).
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 enum GenericReplace {
-    AlwaysReplace{event_type:EventType, replace_with:GenericEvent},
-    ChanceReplace{event_type:EventType, replace_with:GenericEvent, else_message:GenericMessage, chance:f32},
+    AlwaysReplace{event_type:String, relation:ModRelation, replace_with:GenericEvent},
+    ChanceReplace{event_type:String, relation:ModRelation, replace_with:GenericEvent, else_message:GenericMessage, chance:f32},
 }
 
-#[derive(Debug,Deserialize, Serialize)]
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 enum GenericPre {
-    AlwaysEvent (GenericEvent),
-    ChanceEvent (GenericEvent,GenericEvent, f32)
+    // Timing is when what the pre schedules resolves, Immediate unless given. NextTurn holds it until the next turn starts.
+    AlwaysEvent{event_type:String, relation:ModRelation, event:GenericEvent, #[serde(default)] timing:Timing},
+    ChanceEvent{event_type:String, relation:ModRelation, success:GenericEvent, fail:GenericEvent, chance:f32, #[serde(default)] timing:Timing}
 }
-#[derive(Debug,Deserialize, Serialize)]
-enum GenericGet {
-
+// A change to a stat, or to every stat if none is named, made only while its condition holds.
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+struct GenericGet {
+    #[serde(default)]
+    stat:Option<Stats>,
+    op:StatOp,
+    #[serde(default)]
+    condition:Option<StatCondition>
+}
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+enum StatOp {
+    Set (f32),
+    Add (f32),
+    Multiply (f32),
+    Clamp {min:f32, max:f32}
+}
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
+enum StatCondition {
+    HpBelow (f32), // A fraction of max HP, so HpBelow(0.5) holds while below half HP.
+    HpAtLeast (f32)
 }
-#[derive(Debug,Deserialize, Serialize)]
+impl StatOp {
+    fn stage(&self) -> StatStage {
+        match self {
+            StatOp::Set(_) => StatStage::Set,
+            StatOp::Add(_) => StatStage::Add,
+            StatOp::Multiply(_) => StatStage::Multiply,
+            StatOp::Clamp {..} => StatStage::Clamp
+        }
+    }
+    fn apply(&self, statvalue:f32) -> f32 {
+        match self {
+            StatOp::Set(value) => *value,
+            StatOp::Add(value) => statvalue + value,
+            StatOp::Multiply(value) => statvalue * value,
+            // Unlike f32::clamp, doesn't panic on a min above max. Max wins, and validate reports it.
+            StatOp::Clamp {min, max} => statvalue.max(*min).min(*max)
+        }
+    }
+}
+impl StatCondition {
+    fn holds(&self, holder:&StatHolder) -> bool {
+        let fraction = holder.hp as f32 / holder.maxhp as f32;
+        match self {
+            StatCondition::HpBelow(limit) => fraction < *limit,
+            StatCondition::HpAtLeast(limit) => fraction >= *limit
+        }
+    }
+}
+#[derive(Clone, Debug, PartialEq, Deserialize, Serialize)]
 pub struct GenericModifier {
+    #[serde(default)]
+    pub name:String, // Left out in the modifier library, which names it.
+    #[serde(default)]
     replaces:Vec<GenericReplace>,
+    #[serde(default)]
     pres:Vec<GenericPre>,
+    #[serde(default)]
     gets:Vec<GenericGet>
 }
 
-use std::mem::discriminant;
-fn apply_generic_replace (triggering_event:Event, modifier:GenericReplace, game:&Game) -> ReplaceOutcomes {
-    match modifier {
-        GenericReplace::AlwaysReplace { event_type, replace_with } => {
-            if discriminant(&replace_with.event_type) == discriminant(&event_type) {
-                return ReplaceOutcomes::Event {event:replace_with.to_event(&triggering_event, game)};
+fn triggers(event:&Event, relation:ModRelation, event_type:&str, wanted:ModRelation) -> bool {
+    relation == wanted && event.event_type.name() == event_type
+}
+
+impl GenericModifier {
+    // Mistakes serde can't catch, described for the validate command. A trigger that names no event type would
+    // otherwise just never go off, and a clamp with its bounds the wrong way round would always give its max.
+    pub fn problems(&self) -> Vec<String> {
+        let clamps = self.gets.iter().filter_map(|get| match get.op {
+            StatOp::Clamp {min, max} if min > max || min.is_nan() || max.is_nan() => Some(format!("clamps to a min of {} and a max of {}, but the min should be a number no more than the max", min, max)),
+            _ => None
+        });
+        let replaces = self.replaces.iter().map(|r| match r {
+            GenericReplace::AlwaysReplace {event_type, ..} | GenericReplace::ChanceReplace {event_type, ..} => event_type
+        });
+        let pres = self.pres.iter().map(|p| match p {
+            GenericPre::AlwaysEvent {event_type, ..} | GenericPre::ChanceEvent {event_type, ..} => event_type
+        });
+        replaces.chain(pres)
+            .filter(|event_type| !EVENT_NAMES.contains(&event_type.as_str()))
+            .map(|event_type| format!("triggers on \"{}\", which is not an event type", event_type))
+            .chain(clamps)
+            .collect()
+    }
+    // The first replace the event triggers is the one applied.
+    fn replace_event(&self, event:Event, relation:ModRelation) -> ReplaceOutcomes {
+        for replace in &self.replaces {
+            match replace {
+                GenericReplace::AlwaysReplace {event_type, relation:wanted, replace_with} if triggers(&event, relation, event_type, *wanted) => {
+                    return ReplaceOutcomes::Event {event:replace_with.clone().to_event(&event)};
+                }
+                GenericReplace::ChanceReplace {event_type, relation:wanted, replace_with, else_message, chance} if triggers(&event, relation, event_type, *wanted) => {
+                    let success = replace_with.clone().to_event(&event);
+                    let mut fail = event;
+                    fail.message = else_message.clone().to_message(&fail);
+                    return ReplaceOutcomes::Chance {chance:*chance, success, fail};
+                }
+                _ => ()
             }
         }
-        GenericReplace::ChanceReplace { event_type, replace_with, else_message, chance } => {
-            if discriminant(&event_type) == discriminant(&event_type) {
-                let success = replace_with.to_event(&triggering_event, game);
-                let mut fail = triggering_event;
-                fail.message = else_message.to_message(&fail, game);
-                return ReplaceOutcomes::Chance { chance, success, fail};
+        ReplaceOutcomes::Event {event}
+    }
+    // Gets of the same stage apply in the order they're listed.
+    fn get_stat(&self, stat:Stats, statvalue:f32, stage:StatStage, holder:&StatHolder) -> f32 {
+        self.gets.iter()
+            .filter(|get| get.op.stage() == stage && get.stat.is_none_or(|s| s == stat))
+            .filter(|get| get.condition.as_ref().is_none_or(|c| c.holds(holder)))
+            .fold(statvalue, |value, get| get.op.apply(value))
+    }
+    fn pre_event(&self, event:&Event, relation:ModRelation, queue:&mut EventQueue) {
+        for pre in &self.pres {
+            match pre {
+                GenericPre::AlwaysEvent {event_type, relation:wanted, event:generic, timing} if triggers(event, relation, event_type, *wanted) => {
+                    queue.schedule(*timing, generic.clone().to_event(event));
+                }
+                GenericPre::ChanceEvent {event_type, relation:wanted, success, fail, chance, timing} if triggers(event, relation, event_type, *wanted) => {
+                    let success = Box::new(success.clone().to_event(event));
+                    let fail = Box::new(fail.clone().to_event(event));
+                    queue.schedule(*timing, Event::type_only(EventType::Chance {chance:*chance, success, fail}));
+                }
+                _ => ()
             }
         }
-    };
-    return ReplaceOutcomes::Event { event:triggering_event }
+    }
 }
 
-
+// Pheonix, written as a generic modifier.
 pub fn example_event() -> GenericModifier {
     use GenericEntity::*;
     use EventType::*;
@@ -250,20 +504,90 @@ pub fn example_event() -> GenericModifier {
 
     let mut replaces = Vec::new();
     
-    let message = GenericMessage::Custom(vec![EntityName(Source), Phrase("'s Pheonix activates. They are reborn from their ashes!".to_string())]);
-    let replace_with = GenericEvent {event_type:Heal(5), target:Target, source:Target, message};
-    let else_message = GenericMessage::Custom(vec![EntityName(Source), Phrase("'s Pheonix fails. Their ashes scatter to the wind.".to_string())]);
-
-    let modifier = GenericReplace::ChanceReplace{event_type:EventType::Death, chance:0.25, replace_with, else_message};
+    let message = GenericMessage::Custom(vec![EntityName(Target), Phrase("'s Pheonix activates. They are reborn from their ashes!".to_string())]);
+    let replace_with = GenericEvent {event_type:Heal(100), target:Target, source:Target, message};
+    let else_message = GenericMessage::Custom(vec![EntityName(Target), Phrase("'s Pheonix fails. Their ashes scatter to the wind.".to_string())]);
 
-    println!("{}",serde_json::to_string_pretty(&modifier).unwrap());
+    let modifier = GenericReplace::ChanceReplace{event_type:String::from("Death"), relation:ModRelation::Target, chance:0.25, replace_with, else_message};
 
     replaces.push(modifier);
     let pres = Vec::new();
     let gets = Vec::new();
-    GenericModifier { replaces, pres, gets }
+    GenericModifier {name:String::from("Pheonix"), replaces, pres, gets}
 }
 
-// pub fn human_to_modifier() {
-//     let human = "{"name":}"
-// }
\ No newline at end of file
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn generic(gets:Vec<GenericGet>) -> BaseModifier {
+        BaseModifier::Generic(Box::new(GenericModifier {name:String::from("Test"), replaces:Vec::new(), pres:Vec::new(), gets}))
+    }
+    fn get(op:StatOp) -> GenericGet {
+        GenericGet {stat:None, op, condition:None}
+    }
+    fn stat(modifiers:&[BaseModifier], stat:Stats, base:f32, hp:i8) -> f32 {
+        modified_stat(modifiers.iter(), stat, base, &StatHolder {hp, maxhp:10})
+    }
+    fn assert_near(value:f32, expected:f32) {
+        assert!((value - expected).abs() < 1e-5, "{} is not {}", value, expected);
+    }
+
+    // Listed in the opposite order to their stages. In the listed order this would come to 0.3.
+    #[test]
+    fn gets_apply_in_stages_whatever_order_they_are_listed() {
+        let modifier = generic(vec![get(StatOp::Clamp {min:0.0, max:0.7}), get(StatOp::Multiply(2.0)), get(StatOp::Add(0.1)), get(StatOp::Set(0.3))]);
+        assert_near(stat(&[modifier], Stats::Fightiness, 0.5, 10), 0.7);
+    }
+
+    #[test]
+    fn stages_span_every_modifier_held() {
+        let modifiers = [
+            generic(vec![get(StatOp::Clamp {min:0.0, max:1.0})]),
+            generic(vec![get(StatOp::Multiply(2.0))]),
+            BaseModifier::TrailBlazer, // Adds 0.3 to Exploriness.
+            generic(vec![get(StatOp::Add(0.1))]),
+            generic(vec![get(StatOp::Set(0.1))])
+        ];
+        assert_near(stat(&modifiers, Stats::Exploriness, 0.9, 10), 1.0);
+        assert_near(stat(&modifiers, Stats::Magiciness, 0.9, 10), 0.4);
+    }
+
+    #[test]
+    fn gets_in_the_same_stage_apply_in_the_order_listed() {
+        let modifier = generic(vec![get(StatOp::Set(0.2)), get(StatOp::Set(0.4))]);
+        assert_near(stat(&[modifier], Stats::Fightiness, 0.5, 10), 0.4);
+    }
+
+    #[test]
+    fn gets_only_change_the_stat_they_name() {
+        let modifier = generic(vec![GenericGet {stat:Some(Stats::Fightiness), op:StatOp::Add(0.2), condition:None}]);
+        assert_near(stat(std::slice::from_ref(&modifier), Stats::Fightiness, 0.5, 10), 0.7);
+        assert_near(stat(&[modifier], Stats::Magiciness, 0.5, 10), 0.5);
+    }
+
+    #[test]
+    fn conditions_follow_the_holders_hp() {
+        let below = generic(vec![GenericGet {stat:None, op:StatOp::Add(0.3), condition:Some(StatCondition::HpBelow(0.5))}]);
+        let at_least = generic(vec![GenericGet {stat:None, op:StatOp::Add(0.3), condition:Some(StatCondition::HpAtLeast(0.5))}]);
+        assert_near(stat(std::slice::from_ref(&below), Stats::Fightiness, 0.5, 4), 0.8);
+        assert_near(stat(&[below], Stats::Fightiness, 0.5, 5), 0.5);
+        assert_near(stat(std::slice::from_ref(&at_least), Stats::Fightiness, 0.5, 5), 0.8);
+        assert_near(stat(&[at_least], Stats::Fightiness, 0.5, 4), 0.5);
+    }
+
+    // f32::clamp would panic on both of these.
+    #[test]
+    fn backwards_clamps_give_their_max_and_are_reported() {
+        let backwards = generic(vec![get(StatOp::Clamp {min:0.8, max:0.2})]);
+        assert_near(stat(std::slice::from_ref(&backwards), Stats::Fightiness, 0.5, 10), 0.2);
+        let not_a_number = generic(vec![get(StatOp::Clamp {min:f32::NAN, max:0.6})]);
+        assert_near(stat(std::slice::from_ref(&not_a_number), Stats::Fightiness, 0.5, 10), 0.5);
+        for modifier in [backwards, not_a_number] {
+            match modifier {
+                BaseModifier::Generic(generic) => assert_eq!(generic.problems().len(), 1),
+                _ => unreachable!()
+            }
+        }
+    }
+}
@@ -0,0 +1,97 @@
+// Machine readable output: one JSON object per resolved event, for bots and dashboards to read instead of
+// scraping the terminal renderer.
+
+use std::cell::RefCell;
+use std::io::{self, Write};
+use std::rc::Rc;
+
+use serde::{Serialize, Deserialize};
+
+use crate::core_loop::GamePhase;
+use crate::entities::Entity;
+use crate::history::{HistoryEntry, ModifierEffect};
+use crate::listeners::Listener;
+use crate::sim::Game;
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct HpSnapshot {
+    pub name:String,
+    pub hp:i8,
+    pub maxhp:i8,
+    pub active:bool
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GameSnapshot {
+    pub phase:GamePhase,
+    pub depth:i8,
+    pub turns:u32,
+    pub delvers:Vec<HpSnapshot>,
+    pub defenders:Vec<HpSnapshot> // Only the defenders currently in play.
+}
+impl GameSnapshot {
+    pub fn from_game(game:&Game) -> GameSnapshot {
+        let delvers = game.delverteam.delvers.iter()
+            .map(|d| HpSnapshot {name:d.name().to_string(), hp:d.hp, maxhp:d.maxhp, active:d.active})
+            .collect();
+        let defenders = game.defenderteam.active_defenders.iter()
+            .map(|d| HpSnapshot {name:d.name().to_string(), hp:d.hp, maxhp:d.maxhp, active:d.active})
+            .collect();
+        GameSnapshot {phase:game.phase, depth:game.depth, turns:game.turns, delvers, defenders}
+    }
+}
+
+// One line of the JSON Lines stream. The state is taken after the event resolved.
+#[derive(Serialize, Deserialize)]
+pub struct EventRecord {
+    pub seq:usize,
+    pub turn:u32,
+    pub event_type:String,
+    pub source:Entity,
+    pub target:Entity,
+    pub message:String,
+    pub modifiers:Vec<ModifierEffect>,
+    pub state:GameSnapshot
+}
+impl EventRecord {
+    pub fn to_json_line(entry:&HistoryEntry, game:&Game) -> String {
+        let record = EventRecord {
+            seq:entry.seq,
+            turn:entry.turn,
+            event_type:entry.event.event_type.name().to_string(),
+            source:entry.event.source,
+            target:entry.event.target,
+            message:entry.message.clone(),
+            modifiers:entry.modifiers.clone(),
+            state:GameSnapshot::from_game(game)
+        };
+        serde_json::to_string(&record).unwrap()
+    }
+}
+
+// The first error a JsonLinesWriter hit, shared with whoever is running the game so they can stop.
+pub type WriteError = Rc<RefCell<Option<io::Error>>>;
+
+// Writes each event to out as a JSON line as soon as it resolves. If a write fails, like when the reader
+// closes the pipe, the error is kept and nothing more is written.
+pub struct JsonLinesWriter<W:Write> {
+    out:W,
+    error:WriteError
+}
+impl<W:Write> JsonLinesWriter<W> {
+    pub fn new(out:W) -> JsonLinesWriter<W> {
+        JsonLinesWriter {out, error:WriteError::default()}
+    }
+    // Take this before handing the writer to the Sim, to find out later whether the stream broke.
+    pub fn error(&self) -> WriteError {
+        Rc::clone(&self.error)
+    }
+}
+impl<W:Write> Listener for JsonLinesWriter<W> {
+    fn after_event(&mut self, entry:&HistoryEntry, game:&Game) {
+        if self.error.borrow().is_some() {return}
+        if let Err(error) = writeln!(self.out, "{}", EventRecord::to_json_line(entry, game)) {
+            *self.error.borrow_mut() = Some(error);
+        }
+    }
+}
@@ -0,0 +1,133 @@
+// Replays: a game recorded as its seed, the two teams as they were, and everything that happened. Playing a
+// replay back re-runs the game under the current rules and checks it happens the same way, so an edit that
+// changes how games play out shows up as the first event that came out differently.
+
+use std::fmt;
+use std::fs;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Serialize, Deserialize};
+
+use crate::base_entities::{BaseTeam, TeamLoadError};
+use crate::batch::new_sim;
+use crate::core_loop::GamePhase;
+use crate::history::HistoryEntry;
+use crate::sim::Sim;
+use crate::strict::SimError;
+
+#[derive(Serialize, Deserialize)]
+pub struct Replay {
+    pub seed:u64,
+    pub delvers:BaseTeam, // Snapshots, so the replay still works after the team file changes.
+    pub defenders:BaseTeam,
+    pub history:Vec<HistoryEntry>
+}
+
+// Where a replay stopped matching its recording. Either side is None when that game ended first.
+pub struct Divergence {
+    pub seq:usize,
+    pub recorded:Option<HistoryEntry>,
+    pub replayed:Option<HistoryEntry>
+}
+
+pub enum ReplayError {
+    Teams (TeamLoadError), // The recorded teams no longer load, so the game couldn't be started.
+    Sim (SimError), // The replay was strict, and an event failed its checks.
+    Diverged (Box<Divergence>)
+}
+
+impl Replay {
+    // The sim should have been started from these teams and seed, and played to the end.
+    pub fn record(seed:u64, delvers:&BaseTeam, defenders:&BaseTeam, sim:&Sim) -> Replay {
+        let history = sim.history.iter().map(without_colors).collect();
+        Replay {seed, delvers:delvers.clone(), defenders:defenders.clone(), history}
+    }
+    pub fn load(file:&str) -> Result<Replay, String> {
+        let contents = fs::read_to_string(file).map_err(|e| format!("{}: {}", file, e))?;
+        serde_json::from_str(&contents).map_err(|e| format!("{}: {}", file, e))
+    }
+    pub fn save(&self, file:&str) -> Result<(), String> {
+        let contents = serde_json::to_string_pretty(self).unwrap();
+        fs::write(file, contents).map_err(|e| format!("{}: {}", file, e))
+    }
+    // Plays the game again from the seed, checking every event against the recording as it resolves.
+    // Returns the replayed game if it matched all the way through.
+    pub fn verify(&self, strict:bool) -> Result<Sim, ReplayError> {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.seed);
+        let mut sim = new_sim(&self.delvers, &self.defenders).map_err(ReplayError::Teams)?;
+        sim.strict = strict;
+
+        for recorded in &self.history {
+            if sim.game.phase == GamePhase::Finished && sim.eventqueue.is_idle() {
+                return Err(diverged(recorded.seq, Some(recorded), None));
+            }
+            sim.resolve_last_event(&mut rng).map_err(ReplayError::Sim)?;
+            let replayed = sim.history.last().unwrap();
+            if normalized(recorded) != normalized(replayed) {
+                return Err(diverged(recorded.seq, Some(recorded), Some(replayed)));
+            }
+        }
+        // The recording ends once the game does. If the replay is still going, it ran longer.
+        if sim.game.phase != GamePhase::Finished {
+            sim.resolve_last_event(&mut rng).map_err(ReplayError::Sim)?;
+            let replayed = sim.history.last().unwrap();
+            return Err(diverged(replayed.seq, None, Some(replayed)));
+        }
+        Ok(sim)
+    }
+}
+
+fn diverged(seq:usize, recorded:Option<&HistoryEntry>, replayed:Option<&HistoryEntry>) -> ReplayError {
+    ReplayError::Diverged(Box::new(Divergence {seq, recorded:recorded.map(without_colors), replayed:replayed.map(without_colors)}))
+}
+
+// Messages carry terminal colour codes only when stdout is a terminal, so they're left out of comparisons
+// and recordings. Otherwise a game recorded in the terminal wouldn't match itself replayed into a pipe.
+fn normalized(entry:&HistoryEntry) -> String {
+    strip_colors(&serde_json::to_string(entry).unwrap())
+}
+
+fn without_colors(entry:&HistoryEntry) -> HistoryEntry {
+    serde_json::from_str(&normalized(entry)).unwrap()
+}
+
+// Removes escape sequences from serialized JSON, where the escape character is written as \u001b.
+fn strip_colors(json:&str) -> String {
+    let mut stripped = String::with_capacity(json.len());
+    let mut rest = json;
+    while let Some(start) = rest.find("\\u001b[") {
+        stripped.push_str(&rest[..start]);
+        rest = &rest[start + "\\u001b[".len()..];
+        let end = rest.find(|c:char| c.is_ascii_alphabetic()).map_or(rest.len(), |e| e + 1);
+        rest = &rest[end..];
+    }
+    stripped.push_str(rest);
+    stripped
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "The replay diverged from the recording at event {}", self.seq)?;
+        write!(f, "    recorded: ")?;
+        match &self.recorded {
+            Some(entry) => writeln!(f, "{}", serde_json::to_string(entry).unwrap())?,
+            None => writeln!(f, "(the recorded game had ended)")?
+        }
+        write!(f, "    replayed: ")?;
+        match &self.replayed {
+            Some(entry) => write!(f, "{}", serde_json::to_string(entry).unwrap()),
+            None => write!(f, "(the replayed game had ended)")
+        }
+    }
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ReplayError::Teams(error) => write!(f, "The recorded teams could not be loaded: {}", error),
+            ReplayError::Sim(error) => write!(f, "{}", error),
+            ReplayError::Diverged(divergence) => write!(f, "{}", divergence)
+        }
+    }
+}
@@ -1,11 +1,11 @@
 use serde::{Serialize, Deserialize};
-use core::panic;
 use std::ops::Add;
 use std::fmt;
-use rand::Rng;
 
-use crate::{sim::Game, events::{Event, EventType,EventQueue, Outcomes}, messaging::Message, combat::Monster};
-use crate::entities::{Stats, Defender, Entity};
+use crate::{sim::Game, events::{Event, EventType,EventQueue, Outcomes, Timing, Roller, Opposition}, messaging::Message, combat::Monster};
+use crate::entities::{Stats, Entity, Selector};
+use crate::messaging::MessagePart::{Name, Text};
+use crate::modifiers::BaseModifier;
 
 
 #[derive(Serialize, Deserialize)]
@@ -17,9 +17,9 @@ pub enum RoomType {
     Fight {monsters:Vec<Monster>, partyname:String}
 }
 impl RoomType {
-    fn on_enter(&self, _game:&Game,  _room:Entity, _queue:&mut EventQueue) {}
+    pub fn on_enter(&self, _game:&Game,  _room:Entity, _queue:&mut EventQueue) {}
     pub fn attempt_clear(&self, game:&Game,  room:Entity, delver:Entity, queue:&mut EventQueue) {
-        // queue.events.push(EventType::ClearRoom.target(delver,room));
+        // queue.schedule(Timing::Immediate, EventType::ClearRoom.target(delver,room));
         match self {
             RoomType::Empty => {empty::attempt_clear(game, room, delver, queue)}
             RoomType::Arcane => {arcane_ward::attempt_clear(game, room, delver, queue)}
@@ -28,7 +28,7 @@ impl RoomType {
             RoomType::Fight {monsters, partyname} => {fight::attempt_clear(game, room, delver, queue, monsters, partyname.clone())}
         }
     }
-    fn on_exit(&self, _game:&Game,  _room:Entity, _queue:&mut EventQueue) {}
+    pub fn on_exit(&self, _game:&Game,  _room:Entity, _queue:&mut EventQueue) {}
     pub fn base_stat(&self) -> Stats {
         match self {
             RoomType::Empty => empty::base_stat(),
@@ -43,7 +43,7 @@ mod empty {
     use crate::room_types::*;
     pub fn attempt_clear(_game:&Game,  room:Entity, delver:Entity, queue:&mut EventQueue) {
         let event = Event{ event_type:EventType::ClearRoom, source:delver, target:room, message:Message::None};
-        queue.events.push(event);
+        queue.schedule(Timing::Immediate, event);
     }
     pub fn base_stat() -> Stats {
         Stats::Fightiness
@@ -53,22 +53,34 @@ mod empty {
 mod trapped {
     use crate::room_types::*;
     pub fn attempt_clear(game:&Game,  room:Entity, delver:Entity, queue:&mut EventQueue) {
-        let trigger_index = game.rand_target;
-        let trigger_delver = Entity::Delver { index: trigger_index};
+        let trigger_delver = Entity::Selected(Selector::RandomActiveDelver);
 
 
         let message = Message::Custom(delver.to_string(game) + " disarms the traps");
         let success = vec![Event{ event_type:EventType::ClearRoom, source:delver, target:room, message}];
 
-        let message = Message::Custom(trigger_delver.to_string(game) + " triggers a trap room, hurting themselves");
+        let message = Message::Custom(delver.to_string(game) + " disarms the traps, and salvages a healing salve from one of them");
+        let critical_success = vec![
+            Event{event_type:EventType::ClearRoom, source:delver, target:room, message},
+            Event{event_type:EventType::ApplyModifier (BaseModifier::Regenerating {turns:2}), source:room, target:delver, message:Message::None}
+        ];
+
+        let message = Message::Parts(vec![Name(trigger_delver), Text(String::from(" triggers a trap room, hurting themselves"))]);
         let fail = vec![Event{event_type:EventType::Damage (1), source:room, target:trigger_delver, message}];
-        
-        let outcomes = Outcomes {success, fail};
+
+        let message = Message::Parts(vec![Name(trigger_delver), Text(String::from(" sets off a volley of poisoned darts"))]);
+        let critical_fail = vec![
+            Event{event_type:EventType::Damage (1), source:room, target:trigger_delver, message},
+            Event{event_type:EventType::ApplyModifier (BaseModifier::Poisoned {turns:2}), source:room, target:trigger_delver, message:Message::None}
+        ];
+
+        let outcomes = Outcomes {success, fail, critical_success:Some(critical_success), critical_fail:Some(critical_fail)};
 
         let message = Message::Custom(delver.to_string(game) + " attempts to disarm a trap.");
-        let event = Event::type_and_message(EventType::Roll { difficulty: 0.8, stat: base_stat(), outcomes}, message);
+        let roll = EventType::Roll {roller:Roller::Entity(delver), opposition:Opposition::Difficulty(0.8), stat:base_stat(), outcomes:Box::new(outcomes)};
+        let event = Event {event_type:roll, source:delver, target:room, message};
 
-        queue.events.push(event);
+        queue.schedule(Timing::Immediate, event);
     }
     pub fn base_stat() -> Stats {
         Stats::Fightiness
@@ -78,10 +90,10 @@ mod trapped {
 mod bossfight{
     use crate::room_types::*;
     pub fn attempt_clear(_game:&Game,  room:Entity, delver:Entity, queue:&mut EventQueue) {
-        let event = Event{event_type:EventType::StartBossFight, source:room, target:Entity::None, message:Message::None};
-        queue.events.push(event);
         let event = Event{event_type:EventType::ClearRoom, source:delver, target: room, message:Message::None};
-        queue.events.push(event);
+        queue.schedule(Timing::Immediate, event);
+        let event = Event{event_type:EventType::StartBossFight, source:room, target:Entity::None, message:Message::None};
+        queue.schedule(Timing::Immediate, event);
     }
     pub fn base_stat() -> Stats {
         Stats::Fightiness
@@ -92,18 +104,18 @@ mod fight {
     pub fn attempt_clear(_game:&Game,  room:Entity, delver:Entity, queue:&mut EventQueue, monsters:&Vec<Monster>, partyname:String) {
         let mut monsters = (*monsters).clone();
         
-        let event = Event{event_type:EventType::ClearRoom, source:delver, target: room, message:Message::None};
-        queue.events.push(event);
-        let final_monster = monsters.remove(0);
-        let defender = final_monster.to_game_defender();
+        let first_monster = monsters.remove(0);
+        let defender = first_monster.to_game_defender();
         let message = Message::Encounters(partyname);
         let event = Event{event_type:EventType::SpawnDefender(defender), source:room, target: Entity::None, message};
-        queue.events.push(event);
+        queue.schedule(Timing::Immediate, event);
         for m in monsters {
             let defender = m.to_game_defender();
             let event = Event{event_type:EventType::SpawnDefender(defender), source:room, target: Entity::None, message:Message::None};
-            queue.events.push(event);
+            queue.schedule(Timing::Immediate, event);
         }
+        let event = Event{event_type:EventType::ClearRoom, source:delver, target: room, message:Message::None};
+        queue.schedule(Timing::Immediate, event);
 
     }
     pub fn base_stat() -> Stats {
@@ -113,21 +125,31 @@ mod fight {
 mod arcane_ward {
     use crate::room_types::*;
     pub fn attempt_clear(game:&Game,  room:Entity, delver:Entity, queue:&mut EventQueue) {
-        let trigger_index = game.rand_target;
-        let trigger_delver = Entity::Delver { index: trigger_index};
+        let trigger_delver = Entity::Selected(Selector::RandomActiveDelver);
 
         let message = Message::Custom(delver.to_string(game) + " clears the arcane ward");
         let success = vec![Event{ event_type:EventType::ClearRoom, source:delver, target:room, message}];
 
-        let message = Message::Custom(trigger_delver.to_string(game) + " is exploded by a magical wrad.");
+        let message = Message::Custom(delver.to_string(game) + " clears the arcane ward, and gathers its power into a shield");
+        let critical_success = vec![
+            Event{event_type:EventType::ClearRoom, source:delver, target:room, message},
+            Event{event_type:EventType::ApplyModifier (BaseModifier::Shielded {turns:3}), source:room, target:delver, message:Message::None}
+        ];
+
+        let message = Message::Parts(vec![Name(trigger_delver), Text(String::from(" is exploded by a magical wrad."))]);
         let fail = vec![Event{event_type:EventType::Damage (2), source:room, target:trigger_delver, message}];
-        
-        let outcomes = Outcomes {success, fail};
+
+        let message = Message::GainsModifier(trigger_delver, BaseModifier::Cursed);
+        let mut critical_fail = fail.clone();
+        critical_fail.push(Event{event_type:EventType::ApplyModifier (BaseModifier::Cursed), source:room, target:trigger_delver, message});
+
+        let outcomes = Outcomes {success, fail, critical_success:Some(critical_success), critical_fail:Some(critical_fail)};
 
         let message = Message::Custom(delver.to_string(game) + " attempts to clear an arcane ward.");
-        let event = Event::type_and_message(EventType::Roll { difficulty: 0.8, stat: base_stat(), outcomes}, message);
+        let roll = EventType::Roll {roller:Roller::Entity(delver), opposition:Opposition::Difficulty(0.8), stat:base_stat(), outcomes:Box::new(outcomes)};
+        let event = Event {event_type:roll, source:delver, target:room, message};
 
-        queue.events.push(event);
+        queue.schedule(Timing::Immediate, event);
     }
     pub fn base_stat() -> Stats {
         Stats::Magiciness
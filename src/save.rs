@@ -0,0 +1,59 @@
+// Saving and resuming games. A save is the whole Sim plus the rng's exact position, so a resumed game
+// plays out identically to one that never stopped.
+
+use std::fs::{self, File};
+use std::io::Write;
+
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+
+use crate::sim::Sim;
+
+pub const GAME_SAVE:&str = "GameSave.json";
+pub const RNG_SAVE:&str = "rngsave.json";
+
+#[derive(Serialize,Deserialize)]
+pub struct RngSaver {
+    #[serde(default)]
+    pub seed:u64, // The seed the game was started with, as given to --seed.
+    rng_seed:[u8;32],
+    rng_stream:u64,
+    rng_word:u128,
+}
+impl RngSaver {
+    pub fn save_rng(rng:&ChaCha8Rng, seed:u64) -> RngSaver {
+        let rng_seed = rng.get_seed();
+        let rng_stream = rng.get_stream();
+        let rng_word = rng.get_word_pos();
+        RngSaver {seed, rng_seed, rng_stream, rng_word}
+    }
+    pub fn load_rng(&self) -> ChaCha8Rng {
+        let mut rng = ChaCha8Rng::from_seed(self.rng_seed);
+        rng.set_stream(self.rng_stream);
+        rng.set_word_pos(self.rng_word);
+        rng
+    }
+}
+
+// Returns the rng, the seed the game started with, and the game.
+pub fn load(game_save:&str, rng_save:&str) -> Result<(ChaCha8Rng, u64, Sim), String> {
+    let file = fs::read_to_string(rng_save).map_err(|e| format!("{}: {}", rng_save, e))?;
+    let rngsave:RngSaver = serde_json::from_str(&file).map_err(|e| format!("{}: {}", rng_save, e))?;
+
+    let file = fs::read_to_string(game_save).map_err(|e| format!("{}: {}", game_save, e))?;
+    let sim = serde_json::from_str(&file).map_err(|e| format!("{}: {}", game_save, e))?;
+
+    Ok((rngsave.load_rng(), rngsave.seed, sim))
+}
+
+pub fn save(game_save:&str, rng_save:&str, rng:&ChaCha8Rng, seed:u64, sim:&Sim) {
+    let game = serde_json::to_string_pretty(sim).unwrap();
+    let mut file = File::create(game_save).unwrap();
+    write!(file, "{}", game).unwrap();
+
+    let rngsaver = RngSaver::save_rng(rng, seed);
+    let rng = serde_json::to_string(&rngsaver).unwrap();
+    let mut file = File::create(rng_save).unwrap();
+    write!(file, "{}", rng).unwrap();
+}
@@ -1,29 +1,34 @@
 use rand::Rng;
-use rand::rngs::ThreadRng;
-use rand::seq::SliceRandom;
-use rand_chacha::ChaCha8Rng;
-use rand_pcg::Pcg32;
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 
-use crate::room_types::{Coordinate, RoomType};
+use crate::room_types::RoomType;
 use crate::messaging::Message;
-use crate::modifiers::{ReplaceOutcomes, ModToApply, ModRelation};
-use crate::entities::{Entity, Room, Stats, Delver, Defender, DelverTeam, DefenderTeam, Dungeon};
-use crate::events::{EventQueue, Event, EventType, Outcomes};
+use crate::modifiers::{ReplaceOutcomes, ModToApply, ModRelation, BaseModifier};
+use crate::entities::{Entity, Room, Defender, DelverTeam, DefenderTeam};
+use crate::events::{EventQueue, Event, EventType, Timing, Grade, Roller, Opposition};
+use crate::history::{HistoryEntry, ModifierEffect, Effect, Choice};
+use crate::listeners::Listener;
+use crate::strict::{check_event, SimError};
 use crate::core_loop::GamePhase;
 
-use std::collections::HashMap;
+use std::fmt;
 
 use std::{thread, time};
 
-use colored::{Colorize, ColoredString};
+use colored::Colorize;
 
 #[derive(Serialize, Deserialize)]
 pub struct  Sim {
     pub game: Game,
     pub eventqueue:EventQueue,
-    pub finished:bool
+    pub finished:bool,
+    #[serde(default)]
+    pub history:Vec<HistoryEntry>, // Every resolved event, oldest first. Append only.
+    #[serde(skip)]
+    pub listeners:Vec<Box<dyn Listener>>, // Not saved. Whoever resumes a game registers their own.
+    #[serde(skip)]
+    pub strict:bool // Check each event before resolving it. Off unless asked for. See strict.rs.
 }
 
 
@@ -42,7 +47,8 @@ pub struct Game {
     // pub delver_position:Coordinate,
 
     pub last_log_message:String,
-    pub rand_target:usize,
+    #[serde(default)]
+    pub turns:u32,
 }
 
 
@@ -50,14 +56,100 @@ impl Game {
     pub fn new_game(delverteam:DelverTeam, defenderteam:DefenderTeam) -> Game {
         Game {phase:GamePhase::NotStarted,
             delverteam, defenderteam,
-            current_room:Room {complete:false, room_type:RoomType::Empty},
+            current_room:Room {complete:false, room_type:RoomType::Empty, modifiers:Vec::new()}, // Replaced by Room::entrance when the game starts.
             depth:0,
             last_log_message:String::from(""),
-            rand_target:0
+            turns:0
+        }
+    }
+    // The modifiers an entity holds. Empty for entities that can't hold any.
+    pub fn modifiers(&self, entity:Entity) -> &[BaseModifier] {
+        match self.modifiers_of(entity) {
+            Some(modifiers) => modifiers,
+            None => &[]
+        }
+    }
+    // None for entities that can't hold modifiers, or defenders that have since died.
+    pub fn modifiers_mut(&mut self, entity:Entity) -> Option<&mut Vec<BaseModifier>> {
+        match entity {
+            Entity::Delver {index} => self.delverteam.delvers.get_mut(index).map(|d| &mut d.modifiers),
+            Entity::Defender {index} => self.defenderteam.active_defenders.get_mut(index).map(|d| &mut d.modifiers),
+            Entity::DelverTeam => Some(&mut self.delverteam.modifiers),
+            Entity::DefenderTeam => Some(&mut self.defenderteam.modifiers),
+            Entity::Dungeon => Some(&mut self.defenderteam.dungeon.modifiers),
+            Entity::Room => Some(&mut self.current_room.modifiers),
+            _ => None
+        }
+    }
+    // Delvers and defenders that exist and haven't died. Anything else is always active.
+    pub fn is_active(&self, entity:Entity) -> bool {
+        match entity {
+            Entity::Delver {index} => self.delverteam.delvers.get(index).is_some_and(|d| d.active),
+            Entity::Defender {index} => self.defenderteam.active_defenders.get(index).is_some_and(|d| d.active),
+            _ => true
+        }
+    }
+    // Active delvers and defenders, teams, the dungeon and the room, holding at least one timed status.
+    pub fn statused_entities(&self) -> Vec<Entity> {
+        let delvers = self.delverteam.delvers.iter().enumerate()
+            .filter(|(_, d)| d.active && d.modifiers.iter().any(|m| m.is_timed()))
+            .map(|(index, _)| Entity::Delver {index});
+        let defenders = self.defenderteam.active_defenders.iter().enumerate()
+            .filter(|(_, d)| d.active && d.modifiers.iter().any(|m| m.is_timed()))
+            .map(|(index, _)| Entity::Defender {index});
+        let others = [Entity::DelverTeam, Entity::DefenderTeam, Entity::Dungeon, Entity::Room].into_iter()
+            .filter(|entity| self.modifiers(*entity).iter().any(|m| m.is_timed()));
+        delvers.chain(defenders).chain(others).collect()
+    }
+    // Modifiers that apply to the stats of everyone on a team: the team's, the dungeon's and the current room's.
+    pub fn shared_modifiers(&self, team:Entity) -> [&[BaseModifier]; 3] {
+        [self.modifiers(team), self.modifiers(Entity::Dungeon), self.modifiers(Entity::Room)]
+    }
+    fn modifiers_of(&self, entity:Entity) -> Option<&Vec<BaseModifier>> {
+        match entity {
+            Entity::Delver {index} => self.delverteam.delvers.get(index).map(|d| &d.modifiers),
+            Entity::Defender {index} => self.defenderteam.active_defenders.get(index).map(|d| &d.modifiers),
+            Entity::DelverTeam => Some(&self.delverteam.modifiers),
+            Entity::DefenderTeam => Some(&self.defenderteam.modifiers),
+            Entity::Dungeon => Some(&self.defenderteam.dungeon.modifiers),
+            Entity::Room => Some(&self.current_room.modifiers),
+            _ => None
         }
     }
 }
 
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Winner {
+    Delvers, // Made it out the bottom of the dungeon.
+    Defenders // Every delver died.
+}
+
+// Summary of a finished game, for callers that don't watch it being played.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct GameResult {
+    pub winner:Winner,
+    pub depth:i8,
+    pub surviving_delvers:Vec<String>,
+    pub turns:u32
+}
+impl GameResult {
+    pub fn from_game(game:&Game) -> GameResult {
+        let surviving_delvers: Vec<String> = game.delverteam.active_delvers().iter()
+            .map(|i| game.delverteam.delvers[*i].name().to_string())
+            .collect();
+        let winner = if surviving_delvers.is_empty() {Winner::Defenders} else {Winner::Delvers};
+        GameResult {winner, depth:game.depth, surviving_delvers, turns:game.turns}
+    }
+}
+impl fmt::Display for GameResult {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let winner = match self.winner {
+            Winner::Delvers => "Delvers",
+            Winner::Defenders => "Defenders"
+        };
+        write!(f, "{} win at depth {} after {} turns. Survivors: [{}]", winner, self.depth, self.turns, self.surviving_delvers.join(", "))
+    }
+}
 
 pub fn roll(rng: &mut impl Rng, stat:f32) -> f32 {
     let x:f32 = rng.gen(); //There's a better way to do this
@@ -65,13 +157,23 @@ pub fn roll(rng: &mut impl Rng, stat:f32) -> f32 {
 }
 
 impl Sim {
-    pub fn next_frame(&mut self, rng:&mut impl Rng) -> bool {
-        while self.game.last_log_message == "" { 
-            self.resolve_last_event(rng);
+    pub fn new_sim(game:Game) -> Sim {
+        Sim {game, finished:false, eventqueue:EventQueue::new_queue(), history:Vec::new(), listeners:Vec::new(), strict:false}
+    }
+    // Plays the game to the end without rendering or sleeping.
+    pub fn run_headless(&mut self, rng:&mut impl Rng) -> Result<GameResult, SimError> {
+        while self.game.phase != GamePhase::Finished {
+            self.resolve_last_event(rng)?;
+        }
+        Ok(GameResult::from_game(&self.game))
+    }
+    pub fn next_frame(&mut self, rng:&mut impl Rng) -> Result<bool, SimError> {
+        while self.game.last_log_message.is_empty() { 
+            self.resolve_last_event(rng)?;
         };
         self.render();
         self.game.last_log_message = String::from("");
-        return self.game.phase != GamePhase::Finished
+        Ok(self.game.phase != GamePhase::Finished)
     }
 
     
@@ -100,7 +202,7 @@ impl Sim {
             let active = "O".red();
             let inactive = "O";
             for i in 0..p.maxhp {
-                if i+1 <= p.hp {
+                if i < p.hp {
                     print!("{}", active);
                 }
                 else{
@@ -117,7 +219,7 @@ impl Sim {
             let active = "O".red();
             let inactive = "O";
             for i in 0..p.maxhp {
-                if i+1 <= p.hp {
+                if i < p.hp {
                     print!("{}", active);
                 }
                 else{
@@ -129,7 +231,7 @@ impl Sim {
         println!("{}", self.game.last_log_message);
         
         // Printout events, can be enabled for debugging
-        // for e in &self.eventqueue.events {
+        // for e in self.eventqueue.iter() {
         //     print!("{:?}", e.event_type);
         // }
         // println!();
@@ -138,97 +240,175 @@ impl Sim {
         println!()
     }
 
-    pub fn resolve_last_event(&mut self, rng: &mut impl Rng) {
-        let event = match self.eventqueue.events.pop() {
+    // Replaces selectors with whoever they pick. Each selector picks once per history entry, so an event and the
+    // outcomes it sets off agree on who was picked. A selector with nobody to pick gives Entity::None.
+    fn select_entities(&self, rng:&mut impl Rng, event:Event, choices:&mut Vec<Choice>) -> Event {
+        let mut event = event;
+        let mut entities = vec![&mut event.source, &mut event.target];
+        entities.extend(event.message.entities_mut());
+        for entity in entities {
+            if let Entity::Selected(selector) = *entity {
+                *entity = match choices.iter().find(|c| c.selector == selector) {
+                    Some(choice) => choice.entity,
+                    None => {
+                        let picked = selector.select(&self.game, rng).unwrap_or(Entity::None);
+                        choices.push(Choice {selector, entity:picked});
+                        picked
+                    }
+                };
+            }
+        }
+        event
+    }
+    // Damage or healing aimed at a team lands on each of its active members, as events of their own.
+    fn hit_members(&mut self, team:Entity, event_type:EventType, source:Entity) {
+        let members:Vec<Entity> = match team {
+            Entity::DelverTeam => self.game.delverteam.active_delvers().into_iter().map(|index| Entity::Delver {index}).collect(),
+            _ => (0..self.game.defenderteam.active_defenders.len()).map(|index| Entity::Defender {index}).collect()
+        };
+        for target in members {
+            self.eventqueue.schedule(Timing::Immediate, Event {event_type:event_type.clone(), source, target, message:Message::None});
+        }
+    }
+    // Settles who makes a roll before modifiers see it, so the roller's modifiers apply and the history names them.
+    fn pick_rollers(&self, rng:&mut impl Rng, event:Event) -> Event {
+        let mut event = event;
+        if let EventType::Roll {roller, opposition, stat, ..} = &mut event.event_type {
+            event.source = roller.pick(&self.game, *stat, rng);
+            *roller = Roller::Entity(event.source);
+            if let Opposition::Contested(opponent) = opposition {
+                event.target = opponent.pick(&self.game, *stat, rng);
+                *opponent = Roller::Entity(event.target);
+            }
+        }
+        event
+    }
+    pub fn resolve_last_event(&mut self, rng: &mut impl Rng) -> Result<(), SimError> {
+        let event = match self.eventqueue.next_event() {
             Some(n) => n,
             None =>  Event::type_only(EventType::Tick)
         };
-        self.resolve_event(rng,event);
+        self.resolve_event(rng,event)
     }
-    pub fn resolve_event(&mut self, rng: &mut impl Rng, event:Event) {
-        // Janky solution for traps to randomly target.
-        self.game.rand_target = match self.game.delverteam.active_delvers().choose(rng) {
-            Some(i) => *i,
-            None => 100 // Events that can occur with 0 alive delvers should not target a random alive delver until the jank is fixed.
-        };
+    // In strict mode, rejects an event the game can't resolve. Otherwise lets everything through.
+    fn check(&self, event:&Event) -> Result<(), SimError> {
+        if !self.strict {return Ok(())}
+        check_event(&self.game, event).map_err(|problem| SimError::new(problem, event.clone(), self.history.len(), &self.game))
+    }
+    // Resolves one event. It's added to the end of the history.
+    // Errors only come from strict mode, and leave the game part way through the event, so it shouldn't be played on.
+    pub fn resolve_event(&mut self, rng: &mut impl Rng, event:Event) -> Result<(), SimError> {
+        self.check(&event)?;
+        let phase = self.game.phase;
+        let turn = self.game.turns;
+        let mut choices = Vec::new();
+        let event = self.select_entities(rng, event, &mut choices);
+        let event = self.pick_rollers(rng, event);
         // ------------------------- Gather and apply modifiers to event: --------------------------
-        let event: Event = {
+        let (event, effects) = {
             let mut event = event;
+            let mut effects = Vec::new();
             let mut modifiers = Vec::new();
-            match event.target {
-                Entity::Delver {index} => {
-                    let d = &self.game.delverteam.delvers[index];
-                    for modifier in &d.modifiers {
-                        modifiers.push(ModToApply {modifier, relation:ModRelation::Target} );
-                    }
-                },
-                Entity::Defender { index } => {
-                    let d = &self.game.defenderteam.active_defenders[index];
-                    for modifier in &d.modifiers {
-                        modifiers.push(ModToApply {modifier, relation:ModRelation::Target});
-                    }
+            for modifier in self.game.modifiers(event.target) {
+                modifiers.push(ModToApply {modifier, relation:ModRelation::Target});
+            }
+            for modifier in self.game.modifiers(event.source) {
+                modifiers.push(ModToApply {modifier, relation:ModRelation::Source});
+            }
+            // Teams' modifiers see every event involving one of their members, once however many are involved.
+            let mut teams = Vec::new();
+            for team in [event.target.team(), event.source.team()].into_iter().flatten() {
+                if !teams.contains(&team) && team != event.target && team != event.source {
+                    teams.push(team);
                 }
-                _ => ()
-            };
-            match event.source {
-                Entity::Delver {index} => {
-                    let d = &self.game.delverteam.delvers[index];
-                    for modifier in &d.modifiers {
-                        modifiers.push(ModToApply {modifier, relation:ModRelation::Source} );
-                    }
-                },
-                Entity::Defender {index} => {
-                    let d = &self.game.defenderteam.active_defenders[index];
-                    for modifier in &d.modifiers {
-                        modifiers.push(ModToApply {modifier, relation:ModRelation::Source});
+            }
+            for team in teams {
+                for modifier in self.game.modifiers(team) {
+                    modifiers.push(ModToApply {modifier, relation:ModRelation::Team});
+                }
+            }
+            // Every event happens in the dungeon, and in whichever room the delvers are in.
+            for (place, relation) in [(Entity::Dungeon, ModRelation::Dungeon), (Entity::Room, ModRelation::Room)] {
+                if place != event.target && place != event.source {
+                    for modifier in self.game.modifiers(place) {
+                        modifiers.push(ModToApply {modifier, relation});
                     }
                 }
-                _ => ()
-            };
+            }
             // -------------------------- Modifiers' replace_event called. ---------------------
             for m in &modifiers {
                 let (m, relation) = (m.modifier, m.relation);
-                event = 
+                let before = event.clone();
+                let (replaced, chance_failed) =
                 match m.replace_event(event, relation, &self.game, &mut self.eventqueue) {
-                    ReplaceOutcomes::Stop => Event::cancelled(),
-                    ReplaceOutcomes::Event { event } => event,
+                    ReplaceOutcomes::Stop => (Event::cancelled(), false),
+                    ReplaceOutcomes::Event { event } => (event, false),
                     ReplaceOutcomes::Chance { chance, success, fail } => {
-                        if rng.gen::<f32>() < chance {success} else {fail} 
+                        if rng.gen::<f32>() < chance {(success, false)} else {(fail, true)}
                     }
                 };
+                event = replaced;
+                if chance_failed {
+                    effects.push(ModifierEffect {modifier:m.clone(), relation, effect:Effect::ChanceFailed});
+                } else if event != before {
+                    effects.push(ModifierEffect {modifier:m.clone(), relation, effect:Effect::Replaced});
+                }
             }
+            event = self.select_entities(rng, event, &mut choices); // A replacement may bring selectors of its own.
+            self.check(&event)?; // Again, now modifiers have had their say.
             // -------------------------- Modifiers' pre_event called. ---------------------
             for m in &modifiers {
                 let (m, relation) = (m.modifier, m.relation);
+                let queued = self.eventqueue.len();
                 m.pre_event(&event, relation, &self.game, &mut self.eventqueue);
-    
+                if self.eventqueue.len() > queued {
+                    effects.push(ModifierEffect {modifier:m.clone(), relation, effect:Effect::Reacted});
+                }
             }
-            event
+            (event, effects)
         };
 
         // ------------------------ Events Happen -----------------------------------------------------
+        for l in &mut self.listeners {
+            l.before_event(&event, &self.game);
+        }
         self.game.last_log_message = event.message.to_string(&self.game);
-
+        let mut entry = HistoryEntry {
+            seq:self.history.len(),
+            turn, phase,
+            event:event.clone(),
+            message:match event.message {
+                Message::None => String::new(),
+                _ => self.game.last_log_message.clone()
+            },
+            modifiers:effects,
+            choices
+        };
 
 
         match event.event_type {
             EventType::Damage (amount) => {
                 match event.target {
+                    // Only the blow that takes them to 0 kills them, so a Death is never queued twice.
                     Entity::Delver { index } => {
-                        self.game.delverteam.delvers[index].hp -= amount;
-                        if self.game.delverteam.delvers[index].hp <= 0 {
+                        let delver = &mut self.game.delverteam.delvers[index];
+                        let was_alive = delver.hp > 0;
+                        delver.hp -= amount;
+                        if was_alive && delver.hp <= 0 {
                             let event = Event {event_type:EventType::Death, source:event.source, target:event.target, message:Message::Death(event.target)};
-                            self.eventqueue.events.insert(0,event);
+                            self.eventqueue.schedule(Timing::AfterChain, event);
                         }
                     }
                     Entity::Defender {index} => {
-                        let mut defender:&mut Defender = self.game.defenderteam.active_defenders.get_mut(index).unwrap();
+                        let defender:&mut Defender = self.game.defenderteam.active_defenders.get_mut(index).unwrap();
+                        let was_alive = defender.hp > 0;
                         defender.hp -= amount;
-                        if defender.hp <= 0 {
+                        if was_alive && defender.hp <= 0 {
                             let event = Event {event_type:EventType::Death, source:event.source, target:event.target, message:Message::Death(event.target)};
-                            self.eventqueue.events.insert(0,event);
+                            self.eventqueue.schedule(Timing::AfterChain, event);
                         }
                     }
+                    Entity::DelverTeam | Entity::DefenderTeam => self.hit_members(event.target, EventType::Damage(amount), event.source),
                     _ => ()
                 }
             }
@@ -242,12 +422,13 @@ impl Sim {
                         }
                     }
                     Entity::Defender {index}=> {
-                        let mut defender = self.game.defenderteam.active_defenders.get_mut(index).unwrap();
+                        let defender = self.game.defenderteam.active_defenders.get_mut(index).unwrap();
                         defender.hp += amount;
                         if defender.hp > defender.maxhp {
                             defender.hp = defender.maxhp;
                         }
                     }
+                    Entity::DelverTeam | Entity::DefenderTeam => self.hit_members(event.target, EventType::Heal(amount), event.source),
                     _ => ()
                 }
 
@@ -257,14 +438,15 @@ impl Sim {
                     Entity::Delver { index } => {
                         self.game.delverteam.delvers[index].active = false;
                         let alive_delvers = self.game.delverteam.active_delvers();
-                        if alive_delvers.len() == 0 {
-                            self.eventqueue.events.insert(0,Event::type_only(EventType::EndGame));
+                        if alive_delvers.is_empty() {
+                            self.eventqueue.schedule(Timing::EndOfTurn, Event::type_only(EventType::EndGame));
                         }
                     }
                     Entity::Defender {index} => {
                         // self.game.defenderteam.active_defenders[index].active = false;
                         self.game.defenderteam.active_defenders.remove(index);
-                        if self.game.defenderteam.active_defenders.len() == 0 {
+                        // A fight picked against defenders who have all since died doesn't happen.
+                        if self.game.defenderteam.active_defenders.is_empty() && matches!(self.game.phase, GamePhase::Combat {..}) {
                             self.game.phase = GamePhase::TurnStart;
                         }
                     }
@@ -278,12 +460,13 @@ impl Sim {
                 }
                 self.game.depth += 1;
                 match self.game.depth {
-                    5 => self.eventqueue.events.push(Event::type_only(EventType::StartBossFight)),
-                    6.. => self.eventqueue.events.insert(0,Event::type_only(EventType::EndGame)),
-                    _ => self.game.current_room = Room::new_room(rng)
+                    5 => self.eventqueue.schedule(Timing::Immediate, Event::type_only(EventType::StartBossFight)),
+                    6.. => self.eventqueue.schedule(Timing::EndOfTurn, Event::type_only(EventType::EndGame)),
+                    _ => self.game.current_room = Room::new_room(rng, &self.game.defenderteam.dungeon)
                 }
             }
             EventType::StartBossFight => {
+                self.game.current_room = Room::boss_room();
                 let mut defender = self.game.defenderteam.defender.clone().to_game_defender();
                 defender.maxhp = 7;
                 defender.hp = 7;
@@ -300,21 +483,73 @@ impl Sim {
             EventType::SpawnDefender (defender) => {
                 self.game.defenderteam.active_defenders.push(defender);
             }
-            EventType::Roll { difficulty, stat, outcomes} => {
-                let active_delver = self.game.delverteam.choose_delver(stat);
-                
-                let total_stat = Delver::collect_stats(&active_delver, &self.game.delverteam.delvers, stat);
-
-                let mut pushes = outcomes.get(roll(rng, total_stat) > difficulty * rng.gen::<f32>());
-                self.eventqueue.events.append(&mut pushes);
+            // A timed status the target already has is refreshed to whichever has longer left, rather than stacked.
+            EventType::ApplyModifier (modifier) => {
+                if let Some(modifiers) = self.game.modifiers_mut(event.target) {
+                    match modifiers.iter_mut().find(|m| modifier.is_timed() && m.same_kind(&modifier)) {
+                        Some(existing) => existing.refresh(&modifier),
+                        None => modifiers.push(modifier)
+                    }
+                }
+            }
+            EventType::RemoveModifier (modifier) => {
+                if let Some(modifiers) = self.game.modifiers_mut(event.target) {
+                    let exact = modifiers.iter().position(|m| *m == modifier);
+                    if let Some(i) = exact.or_else(|| modifiers.iter().position(|m| m.same_kind(&modifier))) {
+                        modifiers.remove(i);
+                    }
+                }
+            }
+            EventType::TickStatuses => {
+                let mut expired = Vec::new();
+                if let Some(modifiers) = self.game.modifiers_mut(event.target) {
+                    for m in modifiers.iter_mut() {
+                        if m.count_down() {expired.push(m.clone())}
+                    }
+                }
+                for m in expired {
+                    let message = Message::WearsOff(event.target, m.clone());
+                    let event = Event {event_type:EventType::RemoveModifier(m), source:event.target, target:event.target, message};
+                    self.eventqueue.schedule(Timing::Immediate, event);
+                }
+            }
+            EventType::Roll {roller:_, opposition, stat, outcomes} => {
+                let attempt = roll(rng, event.source.collect_stats(&self.game, stat));
+                let against = match opposition {
+                    Opposition::Difficulty(difficulty) => difficulty * rng.gen::<f32>(),
+                    Opposition::Contested(_) => roll(rng, event.target.collect_stats(&self.game, stat))
+                };
+                let outcome = outcomes.get(Grade::from_margin(attempt - against)).into_iter()
+                    .map(|e| self.select_entities(rng, e, &mut entry.choices))
+                    .collect();
+                self.eventqueue.schedule_all(Timing::Immediate, outcome);
             }
             EventType::Chance {chance, success, fail} => {
                 let event = if chance > rng.gen::<f32>() {success} else {fail};
-                self.eventqueue.events.push(*event);
+                let event = self.select_entities(rng, *event, &mut entry.choices);
+                self.eventqueue.schedule(Timing::Immediate, event);
             }
             EventType::EndGame => {self.game.phase = GamePhase::Finished; self.eventqueue.log(Message::Custom(String::from("Game Ended")));}
             EventType::Log => (),
             EventType::Cancelled => ()
         }
+        self.history.push(entry);
+
+        let entry = self.history.last().unwrap();
+        for l in &mut self.listeners {
+            l.after_event(entry, &self.game);
+        }
+        if self.game.phase != phase {
+            for l in &mut self.listeners {
+                l.phase_changed(phase, self.game.phase, &self.game);
+            }
+            if self.game.phase == GamePhase::Finished {
+                let result = GameResult::from_game(&self.game);
+                for l in &mut self.listeners {
+                    l.game_ended(&result, &self.game);
+                }
+            }
         }
+        Ok(())
+    }
 }
\ No newline at end of file
@@ -0,0 +1,111 @@
+// Strict mode: every event is checked against the game before it resolves, and a bad one stops the game
+// with a SimError describing it, instead of a panic somewhere inside the rules. The tests in tests/ turn it on.
+
+use std::error::Error;
+use std::fmt;
+
+use crate::entities::{Entity, Selector};
+use crate::events::{Event, EventType, Roller, Opposition};
+use crate::messaging::Message;
+use crate::output::GameSnapshot;
+use crate::sim::Game;
+
+#[derive(Debug)]
+pub enum EventProblem {
+    MissingEntity {entity:Entity, role:&'static str}, // An index past the end, like a defender that has since died.
+    Inactive {entity:Entity, role:&'static str}, // A delver who has died, still there but out of the game.
+    WrongEntity {entity:Entity, role:&'static str, expected:&'static str},
+    NoActiveMembers {team:Entity}, // A team roll with nobody left to make it.
+    NothingToSelect {selector:Selector},
+    UnexpectedMessage // Tick and Cancelled events aren't logged, so shouldn't carry a message.
+}
+
+#[derive(Debug)]
+pub struct SimError {
+    pub problem:EventProblem,
+    pub event:Box<Event>,
+    pub seq:usize, // Where the event would have gone in the history.
+    pub state:Box<GameSnapshot> // The game when the event was rejected.
+}
+
+// Checks an event can resolve against the game as it is now.
+pub fn check_event(game:&Game, event:&Event) -> Result<(), EventProblem> {
+    check_entity(game, event.source, "source")?;
+    check_entity(game, event.target, "target")?;
+    for entity in event.message.entities() {
+        check_entity(game, entity, "message")?;
+    }
+    match &event.event_type {
+        EventType::Tick | EventType::Cancelled if event.message != Message::None => Err(EventProblem::UnexpectedMessage),
+        EventType::Delve if !matches!(event.source, Entity::Delver {..}) => {
+            Err(EventProblem::WrongEntity {entity:event.source, role:"source", expected:"a delver"})
+        }
+        EventType::Roll {roller, opposition, ..} => {
+            check_roller(game, roller, "roller")?;
+            match opposition {
+                Opposition::Contested(opponent) => check_roller(game, opponent, "opponent"),
+                Opposition::Difficulty(_) => Ok(())
+            }
+        }
+        _ => Ok(())
+    }
+}
+
+fn check_entity(game:&Game, entity:Entity, role:&'static str) -> Result<(), EventProblem> {
+    let exists = match entity {
+        Entity::Delver {index} => index < game.delverteam.delvers.len(),
+        Entity::Defender {index} => index < game.defenderteam.active_defenders.len(),
+        Entity::Selected(selector) if selector.candidates(game).is_empty() => return Err(EventProblem::NothingToSelect {selector}),
+        _ => true
+    };
+    if exists {Ok(())} else {Err(EventProblem::MissingEntity {entity, role})}
+}
+
+// Rolls are made with character stats, so only delvers, defenders and their teams can make them, and only while
+// they're still in the game.
+fn check_roller(game:&Game, roller:&Roller, role:&'static str) -> Result<(), EventProblem> {
+    match roller {
+        Roller::Entity(entity @ (Entity::Delver {..} | Entity::Defender {..})) => {
+            check_entity(game, *entity, role)?;
+            if game.is_active(*entity) {Ok(())} else {Err(EventProblem::Inactive {entity:*entity, role})}
+        }
+        Roller::Entity(entity) => Err(EventProblem::WrongEntity {entity:*entity, role, expected:"a delver or defender"}),
+        Roller::Team {team:Entity::DelverTeam, ..} if game.delverteam.active_delvers().is_empty() => {
+            Err(EventProblem::NoActiveMembers {team:Entity::DelverTeam})
+        }
+        Roller::Team {team:Entity::DefenderTeam, ..} if game.defenderteam.active_defenders.is_empty() => {
+            Err(EventProblem::NoActiveMembers {team:Entity::DefenderTeam})
+        }
+        Roller::Team {team:Entity::DelverTeam | Entity::DefenderTeam, ..} => Ok(()),
+        Roller::Team {team, ..} => Err(EventProblem::WrongEntity {entity:*team, role, expected:"a team"})
+    }
+}
+
+impl SimError {
+    pub fn new(problem:EventProblem, event:Event, seq:usize, game:&Game) -> SimError {
+        SimError {problem, event:Box::new(event), seq, state:Box::new(GameSnapshot::from_game(game))}
+    }
+}
+
+impl fmt::Display for EventProblem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            EventProblem::MissingEntity {entity, role} => write!(f, "its {} {:?} does not exist", role, entity),
+            EventProblem::Inactive {entity, role} => write!(f, "its {} {:?} is no longer in the game", role, entity),
+            EventProblem::WrongEntity {entity, role, expected} => write!(f, "its {} is {:?}, but should be {}", role, entity, expected),
+            EventProblem::NoActiveMembers {team} => write!(f, "{:?} has nobody left to roll", team),
+            EventProblem::NothingToSelect {selector} => write!(f, "it names {}, but there is nobody to pick", selector),
+            EventProblem::UnexpectedMessage => write!(f, "it carries a message, but its type is never logged")
+        }
+    }
+}
+
+impl fmt::Display for SimError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        writeln!(f, "Event {} can't resolve: {}", self.seq, self.problem)?;
+        writeln!(f, "    event: {}", serde_json::to_string(&self.event).unwrap())?;
+        write!(f, "    state: {}", serde_json::to_string(&self.state).unwrap())
+    }
+}
+
+impl Error for SimError {}
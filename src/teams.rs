@@ -1,12 +1,6 @@
 //  GameTeams 
 
 
-use serde::{Deserialize, Serialize};
-use std::{fmt};
-use serde_json::{Value};
-use std::fs;
-use colored::Colorize;
 
-use crate::{modifiers::{BaseModifier}, entities::{Stats, Entity, Delver, Defender}};
 
 
@@ -0,0 +1,257 @@
+// Checks a team file without playing it. Works on the raw JSON rather than deserializing into BaseTeam,
+// so that every problem in the file can be reported at once instead of only the first.
+
+use std::collections::HashSet;
+use std::fmt;
+use std::fs;
+
+use serde_json::{Map, Value};
+
+use crate::base_entities::PARTY_SIZE;
+use crate::modifiers::BaseModifier;
+use crate::library::ModifierLibrary;
+
+pub const MIN_STAT:f64 = 0.0;
+pub const MAX_STAT:f64 = 1.0;
+
+const CHARACTER_FIELDS:[&str; 6] = ["name", "exploriness", "fightiness", "magiciness", "supportiveness", "perm_mods"];
+const CHARACTER_STATS:[&str; 4] = ["exploriness", "fightiness", "magiciness", "supportiveness"];
+const DUNGEON_FIELDS:[&str; 6] = ["name", "twistiness", "deadliness", "lengthiness", "modifiers", "room_modifiers"];
+const ROOM_MODIFIER_FIELDS:[&str; 2] = ["chance", "modifier"];
+const DUNGEON_STATS:[&str; 3] = ["twistiness", "deadliness", "lengthiness"];
+const TEAM_FIELDS:[&str; 6] = ["team_name", "delvers", "dungeon", "defenders", "color", "modifiers"];
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Severity {
+    Error, // The file won't load, or the team can't play.
+    Warning // It loads, but probably isn't what was meant.
+}
+
+#[derive(Debug)]
+pub struct Problem {
+    pub severity:Severity,
+    pub team:Option<String>,
+    pub path:String, // Where in the team, like "delvers[1].perm_mods[0]". Empty for the team as a whole.
+    pub message:String
+}
+impl fmt::Display for Problem {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let severity = match self.severity {
+            Severity::Error => "error",
+            Severity::Warning => "warning"
+        };
+        write!(f, "{}: ", severity)?;
+        if let Some(team) = &self.team {
+            write!(f, "{}: ", team)?;
+        }
+        if !self.path.is_empty() {
+            write!(f, "{}: ", self.path)?;
+        }
+        write!(f, "{}", self.message)
+    }
+}
+
+pub fn validate_file(file:&str, library:&ModifierLibrary) -> Vec<Problem> {
+    let contents = match fs::read_to_string(file) {
+        Ok(contents) => contents,
+        Err(e) => return vec![file_problem(format!("could not read {}: {}", file, e))]
+    };
+    match serde_json::from_str(&contents) {
+        Ok(teams) => validate_teams(&teams, library),
+        Err(e) => vec![file_problem(format!("{} is not valid JSON: {}", file, e))]
+    }
+}
+
+// Modifiers given by id are checked as the library modifier they name.
+pub fn validate_teams(teams:&Value, library:&ModifierLibrary) -> Vec<Problem> {
+    let mut teams = teams.clone();
+    library.resolve_teams(&mut teams);
+    let mut checker = Checker {problems:Vec::new(), team:None};
+    let teams = match teams.as_array() {
+        Some(teams) => teams,
+        None => return vec![file_problem(String::from("the file should contain a list of teams"))]
+    };
+
+    let mut names = HashSet::new();
+    for (i, team) in teams.iter().enumerate() {
+        checker.team = Some(match team.get("team_name").and_then(|n| n.as_str()) {
+            Some(name) => name.to_string(),
+            None => format!("team #{}", i + 1)
+        });
+        let team = match team.as_object() {
+            Some(team) => team,
+            None => {checker.error("", "should be an object"); continue}
+        };
+        checker.unknown_fields(team, "", &TEAM_FIELDS);
+
+        match team.get("team_name") {
+            Some(Value::String(name)) if name.trim().is_empty() => checker.error("team_name", "is empty"),
+            Some(Value::String(name)) => {
+                if !names.insert(name.clone()) {
+                    checker.error("team_name", "is used by more than one team");
+                }
+            }
+            Some(_) => checker.error("team_name", "should be a string"),
+            None => checker.error("team_name", "is missing")
+        }
+
+        let delvers = checker.characters(team, "delvers");
+        let defenders = checker.characters(team, "defenders");
+        match (delvers, defenders) {
+            (Some(0), Some(0)) => checker.error("", "has no delvers and no defenders, so it can neither delve nor defend"),
+            (Some(n), _) if n > 0 && n < PARTY_SIZE => checker.error("delvers", &format!("has {} delvers, but a delving party needs {} to fill every role", n, PARTY_SIZE)),
+            (Some(0), _) => checker.warning("delvers", "is empty, so this team can only defend"),
+            (_, Some(0)) => checker.warning("defenders", "is empty, so this team can only delve"),
+            _ => ()
+        }
+
+        match team.get("dungeon") {
+            Some(Value::Object(dungeon)) => {
+                checker.unknown_fields(dungeon, "dungeon.", &DUNGEON_FIELDS);
+                checker.name(dungeon, "dungeon.");
+                for stat in DUNGEON_STATS {
+                    checker.stat(dungeon, "dungeon.", stat);
+                }
+                if dungeon.contains_key("modifiers") {
+                    checker.modifiers(dungeon.get("modifiers"), "dungeon.modifiers");
+                }
+                if dungeon.contains_key("room_modifiers") {
+                    checker.room_modifiers(dungeon.get("room_modifiers"));
+                }
+            }
+            Some(_) => checker.error("dungeon", "should be an object"),
+            None => checker.error("dungeon", "is missing")
+        }
+
+        checker.color(team.get("color"));
+        if team.contains_key("modifiers") {
+            checker.modifiers(team.get("modifiers"), "modifiers");
+        }
+    }
+    checker.problems
+}
+
+fn file_problem(message:String) -> Problem {
+    Problem {severity:Severity::Error, team:None, path:String::new(), message}
+}
+
+struct Checker {
+    problems:Vec<Problem>,
+    team:Option<String>
+}
+impl Checker {
+    fn error(&mut self, path:&str, message:&str) {
+        self.problems.push(Problem {severity:Severity::Error, team:self.team.clone(), path:path.to_string(), message:message.to_string()});
+    }
+    fn warning(&mut self, path:&str, message:&str) {
+        self.problems.push(Problem {severity:Severity::Warning, team:self.team.clone(), path:path.to_string(), message:message.to_string()});
+    }
+    // serde skips fields it doesn't know, so a misspelt stat shows up as missing. Point at the misspelling too.
+    fn unknown_fields(&mut self, object:&Map<String, Value>, prefix:&str, known:&[&str]) {
+        for key in object.keys() {
+            if !known.contains(&key.as_str()) {
+                self.warning(&format!("{}{}", prefix, key), "is not a field the game knows about, and will be ignored");
+            }
+        }
+    }
+    fn name(&mut self, object:&Map<String, Value>, prefix:&str) {
+        let path = format!("{}name", prefix);
+        match object.get("name") {
+            Some(Value::String(_)) => (),
+            Some(_) => self.error(&path, "should be a string"),
+            None => self.error(&path, "is missing")
+        }
+    }
+    fn stat(&mut self, object:&Map<String, Value>, prefix:&str, stat:&str) {
+        let path = format!("{}{}", prefix, stat);
+        match object.get(stat).map(|s| s.as_f64()) {
+            Some(Some(value)) if (MIN_STAT..=MAX_STAT).contains(&value) => (),
+            Some(Some(value)) => self.error(&path, &format!("is {}, outside the range {} to {}", value, MIN_STAT, MAX_STAT)),
+            Some(None) => self.error(&path, "should be a number"),
+            None => self.error(&path, "is missing")
+        }
+    }
+    // Checks a list of delvers or defenders, returning how many there are if it is a list.
+    fn characters(&mut self, team:&Map<String, Value>, field:&str) -> Option<usize> {
+        let characters = match team.get(field) {
+            Some(Value::Array(characters)) => characters,
+            Some(_) => {self.error(field, "should be a list"); return None}
+            None => {self.error(field, "is missing"); return None}
+        };
+        for (i, character) in characters.iter().enumerate() {
+            let prefix = format!("{}[{}]", field, i);
+            let character = match character.as_object() {
+                Some(character) => character,
+                None => {self.error(&prefix, "should be an object"); continue}
+            };
+            let prefix = prefix + ".";
+            self.unknown_fields(character, &prefix, &CHARACTER_FIELDS);
+            self.name(character, &prefix);
+            for stat in CHARACTER_STATS {
+                self.stat(character, &prefix, stat);
+            }
+            self.modifiers(character.get("perm_mods"), &format!("{}perm_mods", prefix));
+        }
+        Some(characters.len())
+    }
+    fn modifiers(&mut self, modifiers:Option<&Value>, path:&str) {
+        let modifiers = match modifiers {
+            Some(Value::Array(modifiers)) => modifiers,
+            Some(_) => {self.error(path, "should be a list"); return}
+            None => {self.error(path, "is missing"); return}
+        };
+        for (i, modifier) in modifiers.iter().enumerate() {
+            self.modifier(modifier, &format!("{}[{}]", path, i));
+        }
+    }
+    fn modifier(&mut self, modifier:&Value, path:&str) {
+        match serde_json::from_value::<BaseModifier>(modifier.clone()) {
+            Ok(BaseModifier::Generic(generic)) => {
+                for message in generic.problems() {
+                    self.error(path, &message);
+                }
+            }
+            Ok(_) => (),
+            Err(e) => {
+                let message = match modifier {
+                    Value::String(name) => format!("\"{}\" is not a known modifier, or an id in the modifier library", name),
+                    _ => format!("is not a valid modifier: {}", e)
+                };
+                self.error(path, &message);
+            }
+        }
+    }
+    fn room_modifiers(&mut self, rooms:Option<&Value>) {
+        let rooms = match rooms {
+            Some(Value::Array(rooms)) => rooms,
+            _ => {self.error("dungeon.room_modifiers", "should be a list"); return}
+        };
+        for (i, room) in rooms.iter().enumerate() {
+            let prefix = format!("dungeon.room_modifiers[{}]", i);
+            let room = match room.as_object() {
+                Some(room) => room,
+                None => {self.error(&prefix, "should be an object"); continue}
+            };
+            let prefix = prefix + ".";
+            self.unknown_fields(room, &prefix, &ROOM_MODIFIER_FIELDS);
+            self.stat(room, &prefix, "chance");
+            match room.get("modifier") {
+                Some(modifier) => self.modifier(modifier, &format!("{}modifier", prefix)),
+                None => self.error(&format!("{}modifier", prefix), "is missing")
+            }
+        }
+    }
+    fn color(&mut self, color:Option<&Value>) {
+        let channels = match color {
+            Some(Value::Array(channels)) if channels.len() == 3 => channels,
+            Some(_) => {self.error("color", "should be a list of three numbers, red, green and blue"); return}
+            None => {self.error("color", "is missing"); return}
+        };
+        for channel in channels {
+            match channel.as_u64() {
+                Some(c) if c <= 255 => (),
+                _ => {self.error("color", &format!("has channel {}, but each channel should be a whole number from 0 to 255", channel)); return}
+            }
+        }
+    }
+}
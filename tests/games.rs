@@ -0,0 +1,137 @@
+// Whole games played through the library, checking the guarantees the binary relies on.
+
+use std::ops::Range;
+
+use delvers::{BaseTeam, ModifierLibrary, Sim};
+use delvers::entities::Entity;
+use delvers::history::Effect;
+use delvers::modifiers::BaseModifier;
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+use serde_json::json;
+
+fn teams() -> Vec<BaseTeam> {
+    let library = ModifierLibrary::load("Modifiers.json").unwrap();
+    delvers::load_teams("Teams.json", &library).unwrap()
+}
+
+fn strict_sim(delvers:&BaseTeam, defenders:&BaseTeam) -> Sim {
+    let mut sim = delvers::new_sim(delvers, defenders).unwrap();
+    sim.strict = true;
+    sim
+}
+
+fn play_strict(delvers:&BaseTeam, defenders:&BaseTeam, seeds:Range<u64>) {
+    for seed in seeds {
+        let mut rng = ChaCha8Rng::seed_from_u64(seed);
+        let mut sim = strict_sim(delvers, defenders);
+        if let Err(error) = sim.run_headless(&mut rng) {
+            panic!("Seed {}: {}", seed, error);
+        }
+    }
+}
+
+#[test]
+fn strict_games_resolve_every_event() {
+    let teams = teams();
+    play_strict(&teams[0], &teams[1], 0..500);
+}
+
+// Poison ticks at the start of the turn, before combatants are picked, so it kills delvers mid-fight.
+#[test]
+fn strict_games_with_poison_resolve_every_event() {
+    let teams = teams();
+    let mut defenders = teams[1].clone();
+    let venom = json!({"Generic": {"name": "Venom", "pres": [{"AlwaysEvent": {
+        "event_type": "Damage", "relation": "Team",
+        "event": {"event_type": {"ApplyModifier": {"Poisoned": {"turns": 2}}}, "target": "Target", "source": "Source", "message": {"Default": "None"}}
+    }}]}});
+    defenders.modifiers.push(serde_json::from_value(venom).unwrap());
+    play_strict(&teams[0], &defenders, 0..500);
+}
+
+// A defender with no fight in them still has to be picked to fight, whether by their own stats or the dungeon's.
+#[test]
+fn defenders_with_no_fightiness_still_fight() {
+    let teams = teams();
+    let mut hopeless = teams[1].clone();
+    hopeless.defenders[0].fightiness = 0.0;
+    play_strict(&teams[0], &hopeless, 0..100);
+
+    let mut disarming = teams[1].clone();
+    let disarmed = json!({"Generic": {"name": "Disarmed", "gets": [{"stat": "Fightiness", "op": {"Set": 0.0}}]}});
+    disarming.dungeon.modifiers.push(serde_json::from_value(disarmed).unwrap());
+    play_strict(&teams[0], &disarming, 0..100);
+}
+
+// Simulate is a library call, so it can be asked for no games at all, which the binary never does.
+#[test]
+fn simulating_no_games_reports_nothing() {
+    let teams = teams();
+    let report = delvers::simulate(&teams[0], &teams[1], 0, 0).unwrap();
+    assert_eq!(report.win_rate(), 0.0);
+    assert_eq!(report.average_turns(), 0.0);
+    assert_eq!(report.win_rate_interval(), (0.0, 1.0));
+    assert!(report.to_string().starts_with("No games"));
+}
+
+// A generic pre timed for the next turn waits for it: every rest after a delve comes the turn after.
+#[test]
+fn next_turn_pres_wait_for_the_next_turn() {
+    let mut teams = teams();
+    let rest = json!({"Generic": {"name": "Rest", "pres": [{"AlwaysEvent": {
+        "event_type": "Delve", "relation": "Team", "timing": "NextTurn",
+        "event": {"event_type": "Log", "target": "None", "source": "None", "message": {"Custom": [{"Phrase": "The party rests"}]}}
+    }}]}});
+    teams[0].modifiers.push(serde_json::from_value(rest).unwrap());
+    let mut sim = strict_sim(&teams[0], &teams[1]);
+    sim.run_headless(&mut ChaCha8Rng::seed_from_u64(1)).unwrap();
+
+    let delves:Vec<u32> = sim.history.iter().filter(|e| e.event.event_type.name() == "Delve").map(|e| e.turn).collect();
+    let rests:Vec<u32> = sim.history.iter().filter(|e| e.message == "The party rests").map(|e| e.turn).collect();
+    assert!(!rests.is_empty());
+    for (delve, rest) in delves.iter().zip(&rests) {
+        assert_eq!(*rest, delve + 1);
+    }
+}
+
+// Pheonix only fires when it brings someone back. A failed roll is recorded, but the Death still happens.
+#[test]
+fn failed_pheonix_rolls_are_not_activations() {
+    let teams = teams();
+    let pheonix = BaseModifier::Pheonix;
+    let (mut activations, mut rebirths, mut failures) = (0, 0, 0);
+    for seed in 0..100 {
+        let mut sim = strict_sim(&teams[0], &teams[1]);
+        sim.run_headless(&mut ChaCha8Rng::seed_from_u64(seed)).unwrap();
+        activations += sim.modifier_activations(&pheonix);
+        for entry in &sim.history {
+            match entry.modifiers.iter().find(|m| m.modifier == pheonix).map(|m| m.effect) {
+                Some(Effect::Replaced) => {assert_eq!(entry.event.event_type.name(), "Heal"); rebirths += 1}
+                Some(Effect::ChanceFailed) => {assert_eq!(entry.event.event_type.name(), "Death"); failures += 1}
+                _ => ()
+            }
+        }
+    }
+    assert!(rebirths > 0 && failures > 0);
+    assert_eq!(activations, rebirths);
+}
+
+// A defender stunned for the whole game never lands a blow, whether it attacks or is attacked.
+#[test]
+fn stunned_defenders_never_fight_back() {
+    let teams = teams();
+    let mut defenders = teams[1].clone();
+    for defender in &mut defenders.defenders {
+        defender.perm_mods.push(BaseModifier::Stunned {turns:u8::MAX});
+    }
+    let mut attacked_while_stunned = 0;
+    for seed in 0..50 {
+        let mut sim = strict_sim(&teams[0], &defenders);
+        sim.run_headless(&mut ChaCha8Rng::seed_from_u64(seed)).unwrap();
+        let blows = sim.history.iter().filter(|e| e.event.event_type.name() == "Damage" && matches!(e.event.source, Entity::Defender {..}));
+        assert_eq!(blows.count(), 0, "seed {}", seed);
+        attacked_while_stunned += sim.history.iter().filter(|e| e.message.ends_with("can't fight back")).count();
+    }
+    assert!(attacked_while_stunned > 0);
+}
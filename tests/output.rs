@@ -0,0 +1,38 @@
+// The JSON lines stream, as bots reading it see it.
+
+use std::io::{self, Write};
+
+use delvers::output::JsonLinesWriter;
+use delvers::{BaseTeam, GamePhase, ModifierLibrary};
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+// Takes a few lines, then hangs up like `head` does.
+struct Reader {
+    lines:usize
+}
+impl Write for Reader {
+    fn write(&mut self, buf:&[u8]) -> io::Result<usize> {
+        if self.lines == 0 {return Err(io::Error::from(io::ErrorKind::BrokenPipe))}
+        self.lines -= buf.iter().filter(|b| **b == b'\n').count().min(self.lines);
+        Ok(buf.len())
+    }
+    fn flush(&mut self) -> io::Result<()> {
+        Ok(())
+    }
+}
+
+#[test]
+fn closed_stream_stops_writing_without_stopping_the_game() {
+    let library = ModifierLibrary::load("Modifiers.json").unwrap();
+    let teams:Vec<BaseTeam> = delvers::load_teams("Teams.json", &library).unwrap();
+    let mut sim = delvers::new_sim(&teams[0], &teams[1]).unwrap();
+    let writer = JsonLinesWriter::new(Reader {lines:1});
+    let error = writer.error();
+    sim.add_listener(Box::new(writer));
+
+    let mut rng = ChaCha8Rng::seed_from_u64(1);
+    sim.run_headless(&mut rng).unwrap();
+    assert_eq!(sim.game.phase, GamePhase::Finished);
+    assert_eq!(error.take().map(|e| e.kind()), Some(io::ErrorKind::BrokenPipe));
+}
@@ -0,0 +1,60 @@
+// Replays catch games that no longer play out the way they were recorded.
+
+use delvers::replay::{Replay, ReplayError};
+use delvers::{BaseTeam, ModifierLibrary};
+
+fn record(seed:u64) -> Replay {
+    let library = ModifierLibrary::load("Modifiers.json").unwrap();
+    let teams:Vec<BaseTeam> = delvers::load_teams("Teams.json", &library).unwrap();
+    let (_, sim) = delvers::play_headless(&teams[0], &teams[1], seed).unwrap();
+    Replay::record(seed, &teams[0], &teams[1], &sim)
+}
+
+// Goes through JSON, the way a replay file would be edited.
+fn edited(replay:&Replay, edit:impl FnOnce(&mut serde_json::Value)) -> Replay {
+    let mut value = serde_json::to_value(replay).unwrap();
+    edit(&mut value);
+    serde_json::from_value(value).unwrap()
+}
+
+#[test]
+fn recorded_game_verifies() {
+    let replay = record(3);
+    let sim = replay.verify(true).ok().unwrap();
+    assert_eq!(sim.history.len(), replay.history.len());
+}
+
+#[test]
+fn changed_team_diverges_where_the_games_part() {
+    let replay = record(3);
+    let changed = edited(&replay, |r| r["delvers"]["delvers"][0]["fightiness"] = 0.05.into());
+    // Where a game recorded with the changed team first differs from the original.
+    let other = Replay::record(3, &changed.delvers, &changed.defenders, &delvers::play_headless(&changed.delvers, &changed.defenders, 3).unwrap().1);
+    let expected = replay.history.iter().zip(&other.history).position(|(a, b)| a != b).unwrap();
+
+    match changed.verify(true) {
+        Err(ReplayError::Diverged(divergence)) => {
+            assert_eq!(divergence.seq, expected);
+            assert_eq!(divergence.recorded.as_ref(), replay.history.get(expected));
+            assert!(divergence.replayed.is_some());
+        }
+        Err(error) => panic!("{}", error),
+        Ok(_) => panic!("the changed team should have diverged")
+    }
+}
+
+#[test]
+fn replay_that_runs_longer_than_the_recording_diverges() {
+    let mut replay = record(3);
+    let cut = replay.history.len() / 2;
+    replay.history.truncate(cut);
+    match replay.verify(true) {
+        Err(ReplayError::Diverged(divergence)) => {
+            assert_eq!(divergence.seq, cut);
+            assert!(divergence.recorded.is_none());
+            assert_eq!(divergence.replayed.map(|e| e.seq), Some(cut));
+        }
+        Err(error) => panic!("{}", error),
+        Ok(_) => panic!("the replay should have run past the end of the recording")
+    }
+}
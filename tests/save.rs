@@ -0,0 +1,61 @@
+// Saving and resuming, through the library.
+
+use std::env;
+use std::fs;
+
+use delvers::{save, BaseTeam, GamePhase, ModifierLibrary, Sim};
+use rand::prelude::*;
+use rand_chacha::ChaCha8Rng;
+
+fn new_sim() -> Sim {
+    let library = ModifierLibrary::load("Modifiers.json").unwrap();
+    let teams:Vec<BaseTeam> = delvers::load_teams("Teams.json", &library).unwrap();
+    let mut sim = delvers::new_sim(&teams[0], &teams[1]).unwrap();
+    sim.strict = true;
+    sim
+}
+
+// What the binary shows as one frame: events up to and including the next one with a message.
+fn frame(sim:&mut Sim, rng:&mut ChaCha8Rng) -> bool {
+    while sim.game.last_log_message.is_empty() {
+        sim.resolve_last_event(rng).unwrap();
+    }
+    sim.game.last_log_message = String::new();
+    sim.game.phase != GamePhase::Finished
+}
+
+// Autosaving every 4 frames and resuming from each save gives the same game as never stopping.
+#[test]
+fn resumed_game_matches_uninterrupted() {
+    let seed = 3;
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let mut uninterrupted = new_sim();
+    while frame(&mut uninterrupted, &mut rng) {}
+
+    let dir = env::temp_dir();
+    let game_save = dir.join(format!("delvers-test-{}-game.json", std::process::id()));
+    let rng_save = dir.join(format!("delvers-test-{}-rng.json", std::process::id()));
+    let (game_save, rng_save) = (game_save.to_str().unwrap(), rng_save.to_str().unwrap());
+
+    let mut rng = ChaCha8Rng::seed_from_u64(seed);
+    let mut sim = new_sim();
+    let mut frames:u32 = 0;
+    let mut playing = true;
+    while playing {
+        playing = frame(&mut sim, &mut rng);
+        frames += 1;
+        if frames.is_multiple_of(4) {
+            save::save(game_save, rng_save, &rng, seed, &sim);
+            let (loaded_rng, loaded_seed, mut loaded) = save::load(game_save, rng_save).unwrap();
+            assert_eq!(loaded_seed, seed);
+            loaded.strict = true;
+            (rng, sim) = (loaded_rng, loaded);
+        }
+    }
+    let _ = fs::remove_file(game_save);
+    let _ = fs::remove_file(rng_save);
+
+    assert!(frames > 4, "the game should outlast the first save");
+    assert_eq!(sim.history, uninterrupted.history);
+    assert_eq!(sim.game.turns, uninterrupted.game.turns);
+}
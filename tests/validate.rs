@@ -0,0 +1,49 @@
+// Checking team files, through the library. Whatever validate passes should play.
+
+use std::env;
+use std::fs;
+
+use delvers::ModifierLibrary;
+use delvers::validate::{self, Problem, Severity, MIN_STAT};
+use serde_json::Value;
+
+// Teams.json, with the edit made, written where validate and load_teams can both read it.
+fn edited_teams(name:&str, edit:impl FnOnce(&mut Value)) -> String {
+    let mut teams:Value = serde_json::from_str(&fs::read_to_string("Teams.json").unwrap()).unwrap();
+    edit(&mut teams);
+    let file = env::temp_dir().join(format!("delvers-test-{}-{}.json", std::process::id(), name));
+    fs::write(&file, teams.to_string()).unwrap();
+    file.to_str().unwrap().to_string()
+}
+
+// Teams.json has warnings of its own, for the teams that only delve or only defend.
+fn errors(problems:Vec<Problem>) -> Vec<Problem> {
+    problems.into_iter().filter(|p| p.severity == Severity::Error).collect()
+}
+
+// A defender at the bottom of the range is valid, and their team can defend with them.
+#[test]
+fn defender_at_the_lowest_stat_is_valid_and_plays() {
+    let library = ModifierLibrary::load("Modifiers.json").unwrap();
+    let file = edited_teams("lowest-stat", |teams| teams[1]["defenders"][0]["fightiness"] = MIN_STAT.into());
+    let errors = errors(validate::validate_file(&file, &library));
+    let teams = delvers::load_teams(&file, &library);
+    let _ = fs::remove_file(&file);
+
+    assert!(errors.is_empty(), "{:?}", errors);
+    let teams = teams.unwrap();
+    for seed in 0..100 {
+        delvers::play_headless(&teams[0], &teams[1], seed).unwrap();
+    }
+}
+
+#[test]
+fn defender_below_the_lowest_stat_is_an_error() {
+    let library = ModifierLibrary::load("Modifiers.json").unwrap();
+    let file = edited_teams("below-lowest-stat", |teams| teams[1]["defenders"][0]["fightiness"] = (MIN_STAT - 0.1).into());
+    let errors = errors(validate::validate_file(&file, &library));
+    let _ = fs::remove_file(&file);
+
+    assert_eq!(errors.len(), 1, "{:?}", errors);
+    assert_eq!(errors[0].path, "defenders[0].fightiness");
+}